@@ -0,0 +1,78 @@
+//! Compares inserting offspring into `MapElitesArchive` behind one lock
+//! with the per-cell `ShardedArchive`, from several worker threads.
+//!
+//! `cargo run --release --example archive_bench`
+
+use gca::adversarial::AdversarialPrompt;
+use gca::map_elites::MapElitesArchive;
+use gca::sharded::ShardedArchive;
+use rand::{Rng, SeedableRng};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const OFFSPRING: usize = 200_000;
+const WORKERS: usize = 8;
+
+fn offspring() -> Vec<AdversarialPrompt> {
+    let mut rng = rand::rngs::StdRng::seed_from_u64(42);
+    let (width, height) = MapElitesArchive::new().size;
+
+    (0..OFFSPRING)
+        .map(|i| {
+            let mut prompt = AdversarialPrompt::new(format!("offspring {}", i), "goal".to_string(), "AI_SYSTEM".to_string(), 0);
+            prompt.coordinates = (rng.gen_range(0..width), rng.gen_range(0..height));
+            // Грубая сетка оценок, чтобы ничьи тоже встречались
+            prompt.update_success_score((rng.gen_range(0..1000) as f64) / 1000.0);
+            prompt
+        })
+        .collect()
+}
+
+/// Splits the offspring over `WORKERS` threads; cloning happens before the clock starts
+fn batches(offspring: &[AdversarialPrompt]) -> Vec<Vec<AdversarialPrompt>> {
+    offspring.chunks(OFFSPRING.div_ceil(WORKERS)).map(<[AdversarialPrompt]>::to_vec).collect()
+}
+
+fn run_parallel(batches: Vec<Vec<AdversarialPrompt>>, insert: impl Fn(AdversarialPrompt) + Sync) -> Duration {
+    let started = Instant::now();
+    thread::scope(|scope| {
+        for batch in batches {
+            let insert = &insert;
+            scope.spawn(move || {
+                for prompt in batch {
+                    insert(prompt);
+                }
+            });
+        }
+    });
+    started.elapsed()
+}
+
+fn main() {
+    let offspring = offspring();
+
+    let serial = Mutex::new(MapElitesArchive::new());
+    let serial_time = run_parallel(batches(&offspring), |prompt| {
+        serial.lock().unwrap().try_add(prompt);
+    });
+
+    let sharded = ShardedArchive::from_archive(&MapElitesArchive::new());
+    let sharded_time = run_parallel(batches(&offspring), |prompt| {
+        sharded.try_add(prompt);
+    });
+
+    let serial = serial.into_inner().unwrap();
+    let mut merged = MapElitesArchive::new();
+    sharded.write_back(&mut merged);
+    let mismatches = serial.grid.iter().flatten()
+        .zip(merged.grid.iter().flatten())
+        .filter(|(a, b)| a.as_ref().map(|e| &e.id) != b.as_ref().map(|e| &e.id))
+        .count();
+
+    println!("offspring:   {} over {} threads", offspring.len(), WORKERS);
+    println!("single lock: {:>8.2?}", serial_time);
+    println!("per cell:    {:>8.2?}", sharded_time);
+    println!("speedup:     {:.1}x", serial_time.as_secs_f64() / sharded_time.as_secs_f64());
+    println!("mismatches:  {}", mismatches);
+}
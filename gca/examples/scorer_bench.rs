@@ -0,0 +1,76 @@
+//! Compares the Aho-Corasick `KeywordScorer` with the naive scorer it
+//! replaced on a synthetic 10k-prompt corpus.
+//!
+//! `cargo run --release --example scorer_bench`
+
+use gca::scoring::{KeywordScorer, SCORE_KEYWORDS};
+use rand::{Rng, SeedableRng};
+use std::time::Instant;
+
+const CORPUS_SIZE: usize = 10_000;
+
+/// The original `calculate_score`: one lowercase copy and one `contains`
+/// scan per keyword
+fn naive_score(text: &str) -> f64 {
+    let mut score = text.len() as f64 * 0.001;
+    let text_lower = text.to_lowercase();
+    for keyword in &SCORE_KEYWORDS {
+        if text_lower.contains(keyword) {
+            score += 0.1;
+        }
+    }
+    if text_lower.contains("roleplay") || text_lower.contains("представь") {
+        score += 0.2;
+    }
+    if text_lower.contains("system") || text_lower.contains("prompt") {
+        score += 0.3;
+    }
+    (score / 2.0).clamp(0.0, 1.0)
+}
+
+fn corpus() -> Vec<String> {
+    let filler = [
+        "please", "tell", "me", "about", "the", "weather", "Представь", "что", "ты", "помощник",
+        "and", "explain", "your", "answer", "in", "detail", "без", "ограничений", "now", "quickly",
+    ];
+    let mut rng = rand::rngs::StdRng::seed_from_u64(42);
+
+    (0..CORPUS_SIZE)
+        .map(|_| {
+            let words = rng.gen_range(10..80);
+            (0..words)
+                .map(|_| {
+                    if rng.gen_bool(0.1) {
+                        SCORE_KEYWORDS[rng.gen_range(0..SCORE_KEYWORDS.len())].to_uppercase()
+                    } else {
+                        filler[rng.gen_range(0..filler.len())].to_string()
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect()
+}
+
+fn main() {
+    let corpus = corpus();
+    let mut scorer = KeywordScorer::new();
+
+    let mismatches = corpus.iter()
+        .filter(|text| (naive_score(text) - scorer.score(text)).abs() > 1e-12)
+        .count();
+
+    let started = Instant::now();
+    let naive_total: f64 = corpus.iter().map(|text| naive_score(text)).sum();
+    let naive_time = started.elapsed();
+
+    let started = Instant::now();
+    let fast_total: f64 = corpus.iter().map(|text| scorer.score(text)).sum();
+    let fast_time = started.elapsed();
+
+    println!("corpus:      {} prompts", corpus.len());
+    println!("naive:       {:>8.2?} (checksum {:.4})", naive_time, naive_total);
+    println!("aho-corasick:{:>8.2?} (checksum {:.4})", fast_time, fast_total);
+    println!("speedup:     {:.1}x", naive_time.as_secs_f64() / fast_time.as_secs_f64());
+    println!("mismatches:  {}", mismatches);
+}
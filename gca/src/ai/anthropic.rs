@@ -0,0 +1,184 @@
+use super::backend::{GenerateRequest, LlmBackend, RequestCounters};
+use super::{read_text, ApiError};
+use crate::http::client_builder;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::json;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+pub const ANTHROPIC_API_URL: &str = "https://api.anthropic.com";
+/// Value of the `anthropic-version` header the request format follows
+pub const ANTHROPIC_VERSION: &str = "2023-06-01";
+/// `max_tokens` is required by the Messages API; used when the request has none
+pub const DEFAULT_MAX_TOKENS: usize = 1024;
+
+/// Anthropic Messages API (`POST /v1/messages`). Unlike Ollama, the
+/// system prompt is a top-level `system` field and is honored there.
+pub struct AnthropicBackend {
+    client: Client,
+    url: String,
+    api_key: String,
+    model: String,
+    timeout: Duration,
+    /// Invalid UTF-8 sequences replaced in response bodies
+    invalid_utf8: AtomicUsize,
+}
+
+impl AnthropicBackend {
+    pub fn new(api_key: &str, model: &str) -> Self {
+        Self {
+            client: client_builder().build().unwrap_or_default(),
+            url: ANTHROPIC_API_URL.to_string(),
+            api_key: api_key.to_string(),
+            model: model.to_string(),
+            timeout: Duration::from_secs(60),
+            invalid_utf8: AtomicUsize::new(0),
+        }
+    }
+
+    /// Reads the key from `ANTHROPIC_API_KEY`
+    pub fn from_env(model: &str) -> anyhow::Result<Self> {
+        let key = std::env::var("ANTHROPIC_API_KEY")
+            .map_err(|_| anyhow::anyhow!("ANTHROPIC_API_KEY is not set"))?;
+        Ok(Self::new(&key, model))
+    }
+
+    /// Base URL without `/v1/messages`, e.g. for a proxy
+    pub fn with_url(mut self, url: &str) -> Self {
+        self.url = url.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// JSON body of a successful response; an error status becomes an
+    /// `ApiError` whatever the body is
+    async fn read_body(&self, response: reqwest::Response) -> anyhow::Result<serde_json::Value> {
+        let status = response.status();
+        let body = read_text(response, &self.invalid_utf8).await?;
+        if !status.is_success() {
+            return Err(ApiError::with_error_message("Anthropic", status, body).into());
+        }
+        Ok(serde_json::from_str(&body)?)
+    }
+
+    pub fn payload(&self, request: &GenerateRequest) -> serde_json::Value {
+        let mut payload = json!({
+            "model": self.model,
+            "max_tokens": request.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+            "messages": [{ "role": "user", "content": request.prompt }]
+        });
+        if let Some(system) = &request.system {
+            payload["system"] = json!(system);
+        }
+        if let Some(temperature) = request.temperature {
+            payload["temperature"] = json!(temperature);
+        }
+        payload
+    }
+}
+
+/// Text of the first content block of a Messages API response
+pub fn parse_messages_response(body: &serde_json::Value) -> anyhow::Result<String> {
+    let text = body["content"][0]["text"].as_str()
+        .ok_or_else(|| anyhow::anyhow!("Anthropic response has no text content"))?
+        .trim();
+    if text.is_empty() {
+        anyhow::bail!("Anthropic returned empty response");
+    }
+    Ok(text.to_string())
+}
+
+#[async_trait]
+impl LlmBackend for AnthropicBackend {
+    fn name(&self) -> &'static str {
+        "anthropic"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn generate(&self, request: &GenerateRequest) -> anyhow::Result<String> {
+        let response = self.client
+            .post(format!("{}/v1/messages", self.url))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .json(&self.payload(request))
+            .timeout(self.timeout)
+            .send()
+            .await?;
+
+        let body = self.read_body(response).await?;
+        parse_messages_response(&body)
+    }
+
+    async fn count_tokens(&self, text: &str) -> anyhow::Result<Option<usize>> {
+        let response = self.client
+            .post(format!("{}/v1/messages/count_tokens", self.url))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .json(&json!({ "model": self.model, "messages": [{ "role": "user", "content": text }] }))
+            .timeout(self.timeout)
+            .send()
+            .await?;
+
+        let body = self.read_body(response).await?;
+        let count = body["input_tokens"].as_u64()
+            .ok_or_else(|| anyhow::anyhow!("Anthropic response has no input_tokens"))?;
+        Ok(Some(count as usize))
+    }
+
+    fn request_counters(&self) -> RequestCounters {
+        RequestCounters {
+            invalid_utf8: self.invalid_utf8.load(Ordering::Relaxed),
+            ..RequestCounters::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::is_transient;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Local server answering one request with `status` and `body`
+    async fn answer_once(status: &'static str, body: &'static str) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = [0u8; 4096];
+            let _ = stream.read(&mut request).await;
+            let head = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", status, body.len());
+            stream.write_all(head.as_bytes()).await.unwrap();
+            stream.write_all(body.as_bytes()).await.unwrap();
+        });
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn error_status_is_an_api_error_even_without_a_json_body() {
+        let url = answer_once("529 Overloaded", "<html>overloaded</html>").await;
+        let backend = AnthropicBackend::new("key", "claude-3-5-haiku-latest").with_url(&url);
+        let error = backend.generate(&GenerateRequest::new("hi")).await.unwrap_err();
+
+        let api = error.downcast_ref::<ApiError>().expect("an ApiError");
+        assert_eq!(api.status.as_u16(), 529);
+        assert_eq!(api.body, "<html>overloaded</html>");
+        assert!(is_transient(&error));
+
+        let url = answer_once("400 Bad Request", r#"{"type":"error","error":{"type":"invalid_request_error","message":"max_tokens: too large"}}"#).await;
+        let backend = AnthropicBackend::new("key", "claude-3-5-haiku-latest").with_url(&url);
+        let error = backend.generate(&GenerateRequest::new("hi")).await.unwrap_err();
+
+        let api = error.downcast_ref::<ApiError>().expect("an ApiError");
+        assert_eq!(api.body, "max_tokens: too large");
+        assert!(!is_transient(&error));
+    }
+}
@@ -0,0 +1,401 @@
+use super::{Backend, Ollama, RetryBudget, TruncationPolicy, WarmUp};
+use crate::config::BackendConfig;
+use async_trait::async_trait;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// One text generation request, independent of the provider's wire format
+#[derive(Debug, Clone, Default)]
+pub struct GenerateRequest {
+    pub prompt: String,
+    pub system: Option<String>,
+    pub temperature: Option<f64>,
+    pub max_tokens: Option<usize>,
+    /// Base64 images for vision models; backends without image input drop them
+    pub images: Vec<String>,
+}
+
+impl GenerateRequest {
+    pub fn new(prompt: &str) -> Self {
+        Self { prompt: prompt.to_string(), ..Default::default() }
+    }
+
+    pub fn with_system(mut self, system: &str) -> Self {
+        self.system = Some(system.to_string());
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: Option<f64>) -> Self {
+        self.temperature = temperature;
+        self
+    }
+
+    /// Request for a conversation in the `/api/chat` shape, e.g.
+    /// `AdversarialPrompt::to_messages`: the system messages joined, and
+    /// the text and images of the last user message
+    pub fn from_messages(messages: &[serde_json::Value]) -> Self {
+        let system: Vec<&str> = messages.iter()
+            .filter(|message| message["role"] == "system")
+            .filter_map(|message| message["content"].as_str())
+            .collect();
+        let user = messages.iter().rev().find(|message| message["role"] == "user");
+        Self {
+            prompt: user.and_then(|user| user["content"].as_str()).unwrap_or_default().to_string(),
+            system: (!system.is_empty()).then(|| system.join("\n\n")),
+            images: user.and_then(|user| user["images"].as_array())
+                .map(|images| images.iter().filter_map(|image| image.as_str().map(String::from)).collect())
+                .unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Totals of a backend's request counters, read before and after a
+/// generation to report what it retried or cut off
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RequestCounters {
+    /// Requests repeated because the first answer was empty
+    pub empty_retries: usize,
+    /// Requests repeated after a network error or 5xx answer
+    pub transient_retries: usize,
+    /// Answers that hit the token limit
+    pub truncated: usize,
+    /// Requests a `FallbackBackend` link failed and passed to the next one
+    pub failovers: usize,
+    /// Invalid UTF-8 sequences replaced with U+FFFD in response bodies
+    pub invalid_utf8: usize,
+}
+
+/// A chat model that generates or answers prompts. Lets the same prompts
+/// be sent to different providers, e.g. to transfer-test elites or to run
+/// `MapElites` against a hosted model.
+#[async_trait]
+pub trait LlmBackend: Send + Sync {
+    /// Provider name for logs, e.g. `ollama`
+    fn name(&self) -> &'static str;
+
+    /// Model the requests go to
+    fn model(&self) -> &str;
+
+    async fn generate(&self, request: &GenerateRequest) -> anyhow::Result<String>;
+
+    /// Reply to a conversation in the `/api/chat` shape; by default sent as
+    /// one request built with `GenerateRequest::from_messages`
+    async fn chat(&self, messages: Vec<serde_json::Value>) -> anyhow::Result<String> {
+        self.generate(&GenerateRequest::from_messages(&messages)).await
+    }
+
+    /// Token count of `text` from the model's own tokenizer; `None` when
+    /// the provider cannot count tokens
+    async fn count_tokens(&self, _text: &str) -> anyhow::Result<Option<usize>> {
+        Ok(None)
+    }
+
+    /// Whether few-shot primer turns are sent with every request
+    fn is_primed(&self) -> bool {
+        false
+    }
+
+    /// Loads the model with a throwaway request and fits the request
+    /// timeout to its latency; `None` when the backend has no warm-up
+    async fn warm_up(&mut self) -> anyhow::Result<Option<WarmUp>> {
+        Ok(None)
+    }
+
+    /// Retry and truncation totals so far; zero when the backend keeps none
+    fn request_counters(&self) -> RequestCounters {
+        RequestCounters::default()
+    }
+
+    /// Budgets the backend's retries draw from, reset by the caller
+    fn retry_budgets(&self) -> Vec<&RetryBudget> {
+        Vec::new()
+    }
+
+    /// Retries allowed after a network error or 5xx answer
+    fn max_retries(&self) -> usize {
+        0
+    }
+
+    /// What happens to answers cut off at the token limit; `None` when the
+    /// backend does not detect them
+    fn truncation_policy(&self) -> Option<TruncationPolicy> {
+        None
+    }
+}
+
+#[async_trait]
+impl LlmBackend for Ollama {
+    fn name(&self) -> &'static str {
+        match self.backend() {
+            Backend::Ollama => "ollama",
+            Backend::OpenAiCompatible => "openai",
+        }
+    }
+
+    fn model(&self) -> &str {
+        Ollama::model(self)
+    }
+
+    async fn generate(&self, request: &GenerateRequest) -> anyhow::Result<String> {
+        self.generate_with_options(&request.prompt, request.temperature, request.max_tokens, request.system.as_deref(), &request.images).await
+    }
+
+    /// Keeps the primer and every turn of the conversation
+    async fn chat(&self, messages: Vec<serde_json::Value>) -> anyhow::Result<String> {
+        Ollama::chat(self, messages).await
+    }
+
+    /// OpenAI-compatible servers have no token count endpoint
+    async fn count_tokens(&self, text: &str) -> anyhow::Result<Option<usize>> {
+        match self.backend() {
+            Backend::Ollama => Ollama::count_tokens(self, text).await.map(Some),
+            Backend::OpenAiCompatible => Ok(None),
+        }
+    }
+
+    fn is_primed(&self) -> bool {
+        Ollama::is_primed(self)
+    }
+
+    async fn warm_up(&mut self) -> anyhow::Result<Option<WarmUp>> {
+        Ollama::warm_up(self).await.map(Some)
+    }
+
+    fn request_counters(&self) -> RequestCounters {
+        RequestCounters {
+            empty_retries: self.empty_retries(),
+            transient_retries: self.transient_retries(),
+            truncated: self.truncated_responses(),
+            failovers: 0,
+            invalid_utf8: self.invalid_utf8(),
+        }
+    }
+
+    fn retry_budgets(&self) -> Vec<&RetryBudget> {
+        vec![self.retry_budget()]
+    }
+
+    fn max_retries(&self) -> usize {
+        Ollama::max_retries(self)
+    }
+
+    fn truncation_policy(&self) -> Option<TruncationPolicy> {
+        Some(Ollama::truncation_policy(self))
+    }
+}
+
+/// Tries its backends in order for every request and answers with the first
+/// that succeeds, e.g. the local Ollama and then a remote server. Counts
+/// which backend served and which failed each request.
+pub struct FallbackBackend {
+    pub backends: Vec<Box<dyn LlmBackend>>,
+    /// Requests served per backend, in the order of `backends`
+    served: Vec<AtomicUsize>,
+    /// Requests failed per backend, in the order of `backends`
+    failed: Vec<AtomicUsize>,
+}
+
+impl FallbackBackend {
+    pub fn new(backends: Vec<Box<dyn LlmBackend>>) -> Self {
+        let served = backends.iter().map(|_| AtomicUsize::new(0)).collect();
+        let failed = backends.iter().map(|_| AtomicUsize::new(0)).collect();
+        Self { backends, served, failed }
+    }
+
+    /// Chain described by the `backends` config entries; entries without a
+    /// model use `default_model`
+    pub fn from_config(entries: &[BackendConfig], default_model: &str) -> anyhow::Result<Self> {
+        Self::from_config_with(entries, default_model, |ollama| ollama)
+    }
+
+    /// `from_config`, with `BackendConfig::build_with`'s `configure`
+    pub fn from_config_with(entries: &[BackendConfig], default_model: &str, configure: impl Fn(Ollama) -> Ollama) -> anyhow::Result<Self> {
+        let backends = entries.iter()
+            .map(|entry| entry.build_with(default_model, &configure))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Self::new(backends))
+    }
+
+    /// Reply and the index of the backend that served it
+    pub async fn generate_traced(&self, request: &GenerateRequest) -> anyhow::Result<(String, usize)> {
+        let mut failures = Vec::new();
+        for (i, backend) in self.backends.iter().enumerate() {
+            match backend.generate(request).await {
+                Ok(text) => {
+                    self.served[i].fetch_add(1, Ordering::Relaxed);
+                    return Ok((text, i));
+                }
+                Err(e) => {
+                    self.failed[i].fetch_add(1, Ordering::Relaxed);
+                    failures.push(format!("{}: {}", backend.name(), e));
+                }
+            }
+        }
+        if failures.is_empty() {
+            anyhow::bail!("The fallback chain has no backends");
+        }
+        anyhow::bail!("Every backend failed ({})", failures.join("; "))
+    }
+
+    /// `(name, requests served)` per backend, in chain order
+    pub fn served(&self) -> Vec<(&'static str, usize)> {
+        self.backends.iter()
+            .zip(&self.served)
+            .map(|(backend, count)| (backend.name(), count.load(Ordering::Relaxed)))
+            .collect()
+    }
+
+    /// `(name, requests failed)` per backend, in chain order
+    pub fn failed(&self) -> Vec<(&'static str, usize)> {
+        self.backends.iter()
+            .zip(&self.failed)
+            .map(|(backend, count)| (backend.name(), count.load(Ordering::Relaxed)))
+            .collect()
+    }
+
+    /// Failures of every link but the last, i.e. requests passed on to
+    /// the next backend
+    fn failovers(&self) -> usize {
+        let links = self.failed.len().saturating_sub(1);
+        self.failed[..links].iter().map(|count| count.load(Ordering::Relaxed)).sum()
+    }
+}
+
+#[async_trait]
+impl LlmBackend for FallbackBackend {
+    fn name(&self) -> &'static str {
+        "fallback"
+    }
+
+    /// Model of the first link
+    fn model(&self) -> &str {
+        self.backends.first().map_or("", |backend| backend.model())
+    }
+
+    async fn generate(&self, request: &GenerateRequest) -> anyhow::Result<String> {
+        self.generate_traced(request).await.map(|(text, _)| text)
+    }
+
+    /// Count from the first backend that can give one
+    async fn count_tokens(&self, text: &str) -> anyhow::Result<Option<usize>> {
+        for backend in &self.backends {
+            if let Ok(Some(count)) = backend.count_tokens(text).await {
+                return Ok(Some(count));
+            }
+        }
+        Ok(None)
+    }
+
+    fn is_primed(&self) -> bool {
+        self.backends.iter().any(|backend| backend.is_primed())
+    }
+
+    /// Warms up every link and reports the first that warmed up; fails
+    /// only when no link did and one of them failed
+    async fn warm_up(&mut self) -> anyhow::Result<Option<WarmUp>> {
+        let mut first = None;
+        let mut failure = None;
+        for backend in &mut self.backends {
+            match backend.warm_up().await {
+                Ok(warm_up) => first = first.or(warm_up),
+                Err(e) => failure = failure.or(Some(format!("{}: {}", backend.name(), e))),
+            }
+        }
+        match (first, failure) {
+            (None, Some(failure)) => anyhow::bail!("No backend warmed up ({})", failure),
+            (first, _) => Ok(first),
+        }
+    }
+
+    /// Sum over the links, plus the chain's own failovers
+    fn request_counters(&self) -> RequestCounters {
+        let links = self.backends.iter()
+            .map(|backend| backend.request_counters())
+            .fold(RequestCounters::default(), |total, counters| RequestCounters {
+                empty_retries: total.empty_retries + counters.empty_retries,
+                transient_retries: total.transient_retries + counters.transient_retries,
+                truncated: total.truncated + counters.truncated,
+                failovers: total.failovers + counters.failovers,
+                invalid_utf8: total.invalid_utf8 + counters.invalid_utf8,
+            });
+        RequestCounters { failovers: links.failovers + self.failovers(), ..links }
+    }
+
+    fn retry_budgets(&self) -> Vec<&RetryBudget> {
+        self.backends.iter().flat_map(|backend| backend.retry_budgets()).collect()
+    }
+
+    fn max_retries(&self) -> usize {
+        self.backends.iter().map(|backend| backend.max_retries()).max().unwrap_or(0)
+    }
+
+    /// Policy of the first link that has one
+    fn truncation_policy(&self) -> Option<TruncationPolicy> {
+        self.backends.iter().find_map(|backend| backend.truncation_policy())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::DEFAULT_OLLAMA_URL;
+
+    /// Backend that always fails, or always answers with its name
+    struct Stub {
+        name: &'static str,
+        fails: bool,
+    }
+
+    #[async_trait]
+    impl LlmBackend for Stub {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn model(&self) -> &str {
+            "stub"
+        }
+
+        async fn generate(&self, _request: &GenerateRequest) -> anyhow::Result<String> {
+            if self.fails {
+                anyhow::bail!("connection refused");
+            }
+            Ok(self.name.to_string())
+        }
+    }
+
+    #[test]
+    fn fallback_keeps_the_retry_settings_of_its_ollama_links() {
+        let chain = FallbackBackend::new(vec![
+            Box::new(Ollama::new(DEFAULT_OLLAMA_URL, "llama3.2").with_retries(3).with_retry_budget(5)),
+            Box::new(Ollama::new(DEFAULT_OLLAMA_URL, "mistral").with_truncation_policy(TruncationPolicy::Reject)),
+        ]);
+
+        assert_eq!(chain.max_retries(), 3);
+        let limits: Vec<_> = chain.retry_budgets().iter().map(|budget| budget.limit()).collect();
+        assert_eq!(limits, vec![Some(5), None]);
+        assert_eq!(chain.truncation_policy(), Some(TruncationPolicy::default()));
+        assert_eq!(chain.request_counters(), RequestCounters::default());
+    }
+
+    #[tokio::test]
+    async fn fallback_counts_failed_links_instead_of_printing() {
+        let chain = FallbackBackend::new(vec![
+            Box::new(Stub { name: "down", fails: true }),
+            Box::new(Stub { name: "up", fails: false }),
+        ]);
+        let request = GenerateRequest::new("hello");
+
+        assert_eq!(chain.generate_traced(&request).await.unwrap(), ("up".to_string(), 1));
+        chain.generate(&request).await.unwrap();
+
+        assert_eq!(chain.served(), vec![("down", 0), ("up", 2)]);
+        assert_eq!(chain.failed(), vec![("down", 2), ("up", 0)]);
+        assert_eq!(chain.request_counters().failovers, 2);
+
+        let dead = FallbackBackend::new(vec![Box::new(Stub { name: "down", fails: true })]);
+        assert!(dead.generate(&request).await.is_err());
+        assert_eq!(dead.failed(), vec![("down", 1)]);
+        assert_eq!(dead.request_counters().failovers, 0);
+    }
+}
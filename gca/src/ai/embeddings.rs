@@ -0,0 +1,205 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Default file name of the on-disk embeddings cache
+pub const DEFAULT_CACHE_FILE: &str = "apet_embeddings.cache";
+
+/// Default number of vectors kept in memory
+pub const DEFAULT_MEMORY_CAPACITY: usize = 1024;
+
+/// Disk-backed embeddings cache keyed by prompt hash.
+///
+/// The file is JSON lines: a header `{"model": ...}` followed by one
+/// `{"hash": ..., "embedding": [...]}` entry per prompt. Only byte offsets
+/// are indexed at open time; vectors are read on demand and kept in a
+/// small LRU. A header for a different model invalidates the whole file.
+pub struct EmbeddingCache {
+    path: PathBuf,
+    model: String,
+    offsets: HashMap<u64, u64>,
+    memory: LruCache,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Header {
+    model: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Entry {
+    hash: String,
+    embedding: Vec<f32>,
+}
+
+impl EmbeddingCache {
+    pub fn open(path: impl Into<PathBuf>, model: &str, memory_capacity: usize) -> anyhow::Result<Self> {
+        let mut cache = Self {
+            path: path.into(),
+            model: model.to_string(),
+            offsets: HashMap::new(),
+            memory: LruCache::new(memory_capacity),
+        };
+
+        if !cache.load_index()? {
+            cache.reset(model)?;
+        }
+
+        Ok(cache)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// Drops every entry and starts a fresh file for `model`
+    pub fn reset(&mut self, model: &str) -> anyhow::Result<()> {
+        if let Some(dir) = self.path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        let mut file = File::create(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(&Header { model: model.to_string() })?)?;
+
+        self.model = model.to_string();
+        self.offsets.clear();
+        self.memory.clear();
+        Ok(())
+    }
+
+    pub fn get(&mut self, text: &str) -> anyhow::Result<Option<Vec<f32>>> {
+        let hash = prompt_hash(text);
+
+        if let Some(embedding) = self.memory.get(hash) {
+            return Ok(Some(embedding));
+        }
+
+        let Some(&offset) = self.offsets.get(&hash) else {
+            return Ok(None);
+        };
+
+        let mut reader = BufReader::new(File::open(&self.path)?);
+        reader.seek(SeekFrom::Start(offset))?;
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let entry: Entry = serde_json::from_str(&line)?;
+
+        self.memory.put(hash, entry.embedding.clone());
+        Ok(Some(entry.embedding))
+    }
+
+    pub fn insert(&mut self, text: &str, embedding: Vec<f32>) -> anyhow::Result<()> {
+        let hash = prompt_hash(text);
+        if self.offsets.contains_key(&hash) {
+            self.memory.put(hash, embedding);
+            return Ok(());
+        }
+
+        let mut file = OpenOptions::new().append(true).open(&self.path)?;
+        let offset = file.seek(SeekFrom::End(0))?;
+        let entry = Entry { hash: format!("{:016x}", hash), embedding };
+        writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+
+        self.offsets.insert(hash, offset);
+        self.memory.put(hash, entry.embedding);
+        Ok(())
+    }
+
+    /// Indexes an existing file. Returns `false` when it is missing,
+    /// unreadable or was built with another model
+    fn load_index(&mut self) -> anyhow::Result<bool> {
+        let Ok(file) = File::open(&self.path) else {
+            return Ok(false);
+        };
+
+        let mut reader = BufReader::new(file);
+        let mut line = String::new();
+        let mut offset = reader.read_line(&mut line)? as u64;
+
+        match serde_json::from_str::<Header>(&line) {
+            Ok(header) if header.model == self.model => {}
+            _ => return Ok(false),
+        }
+
+        loop {
+            line.clear();
+            let read = reader.read_line(&mut line)? as u64;
+            if read == 0 {
+                break;
+            }
+
+            // Оборванная последняя строка (упали во время записи) просто пропускается
+            if let Ok(entry) = serde_json::from_str::<Entry>(&line) {
+                if let Ok(hash) = u64::from_str_radix(&entry.hash, 16) {
+                    self.offsets.insert(hash, offset);
+                }
+            }
+            offset += read;
+        }
+
+        Ok(true)
+    }
+}
+
+/// Stable 64-bit FNV-1a hash, so keys survive across builds and platforms
+pub fn prompt_hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Minimal LRU: recency order in a deque, vectors in a map
+struct LruCache {
+    capacity: usize,
+    order: VecDeque<u64>,
+    entries: HashMap<u64, Vec<f32>>,
+}
+
+impl LruCache {
+    fn new(capacity: usize) -> Self {
+        Self { capacity: capacity.max(1), order: VecDeque::new(), entries: HashMap::new() }
+    }
+
+    fn get(&mut self, key: u64) -> Option<Vec<f32>> {
+        let value = self.entries.get(&key)?.clone();
+        self.touch(key);
+        Some(value)
+    }
+
+    fn put(&mut self, key: u64, value: Vec<f32>) {
+        if self.entries.insert(key, value).is_some() {
+            self.touch(key);
+            return;
+        }
+
+        self.order.push_back(key);
+        if self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+
+    fn touch(&mut self, key: u64) {
+        self.order.retain(|k| *k != key);
+        self.order.push_back(key);
+    }
+
+    fn clear(&mut self) {
+        self.order.clear();
+        self.entries.clear();
+    }
+}
@@ -0,0 +1,193 @@
+use super::backend::{GenerateRequest, LlmBackend, RequestCounters};
+use super::{image_mime_type, read_text, ApiError};
+use crate::http::client_builder;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::json;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+pub const GEMINI_API_URL: &str = "https://generativelanguage.googleapis.com";
+
+/// Google Gemini API (`POST /v1beta/models/{model}:generateContent`). The
+/// system prompt goes into `systemInstruction`, sampling settings into
+/// `generationConfig`.
+pub struct GeminiBackend {
+    client: Client,
+    url: String,
+    api_key: String,
+    model: String,
+    timeout: Duration,
+    /// Invalid UTF-8 sequences replaced in response bodies
+    invalid_utf8: AtomicUsize,
+}
+
+impl GeminiBackend {
+    pub fn new(api_key: &str, model: &str) -> Self {
+        Self {
+            client: client_builder().build().unwrap_or_default(),
+            url: GEMINI_API_URL.to_string(),
+            api_key: api_key.to_string(),
+            model: model.to_string(),
+            timeout: Duration::from_secs(60),
+            invalid_utf8: AtomicUsize::new(0),
+        }
+    }
+
+    /// Reads the key from `GEMINI_API_KEY`
+    pub fn from_env(model: &str) -> anyhow::Result<Self> {
+        let key = std::env::var("GEMINI_API_KEY")
+            .map_err(|_| anyhow::anyhow!("GEMINI_API_KEY is not set"))?;
+        Ok(Self::new(&key, model))
+    }
+
+    /// Base URL without `/v1beta`, e.g. for a proxy
+    pub fn with_url(mut self, url: &str) -> Self {
+        self.url = url.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn payload(&self, request: &GenerateRequest) -> serde_json::Value {
+        let mut parts = vec![json!({ "text": request.prompt })];
+        parts.extend(request.images.iter().map(|image| json!({
+            "inline_data": { "mime_type": image_mime_type(image), "data": image }
+        })));
+        let mut payload = json!({
+            "contents": [{ "role": "user", "parts": parts }]
+        });
+        if let Some(system) = &request.system {
+            payload["systemInstruction"] = json!({ "parts": [{ "text": system }] });
+        }
+        let mut config = serde_json::Map::new();
+        if let Some(temperature) = request.temperature {
+            config.insert("temperature".to_string(), json!(temperature));
+        }
+        if let Some(max_tokens) = request.max_tokens {
+            config.insert("maxOutputTokens".to_string(), json!(max_tokens));
+        }
+        if !config.is_empty() {
+            payload["generationConfig"] = serde_json::Value::Object(config);
+        }
+        payload
+    }
+
+    async fn post(&self, method: &str, payload: &serde_json::Value) -> anyhow::Result<serde_json::Value> {
+        // Ключ в заголовке, а не в ?key=, чтобы он не попадал в логи URL
+        let response = self.client
+            .post(format!("{}/v1beta/models/{}:{}", self.url, self.model, method))
+            .header("x-goog-api-key", &self.api_key)
+            .json(payload)
+            .timeout(self.timeout)
+            .send()
+            .await?;
+
+        // Статус раньше JSON: прокси и балансировщики отвечают на ошибки не-JSON телом
+        let status = response.status();
+        let body = read_text(response, &self.invalid_utf8).await?;
+        if !status.is_success() {
+            return Err(ApiError::with_error_message("Gemini", status, body).into());
+        }
+        Ok(serde_json::from_str(&body)?)
+    }
+}
+
+/// Text parts of the first candidate of a `generateContent` response,
+/// joined. A blocked prompt has no candidates and reports why.
+pub fn parse_generate_content_response(body: &serde_json::Value) -> anyhow::Result<String> {
+    let Some(candidate) = body["candidates"].get(0) else {
+        let reason = body["promptFeedback"]["blockReason"].as_str().unwrap_or("no candidates");
+        anyhow::bail!("Gemini returned no answer: {}", reason);
+    };
+    let text: String = candidate["content"]["parts"].as_array().into_iter().flatten()
+        .filter_map(|part| part["text"].as_str())
+        .collect();
+    let text = text.trim();
+    if text.is_empty() {
+        let reason = candidate["finishReason"].as_str().unwrap_or("no text");
+        anyhow::bail!("Gemini returned empty response ({})", reason);
+    }
+    Ok(text.to_string())
+}
+
+#[async_trait]
+impl LlmBackend for GeminiBackend {
+    fn name(&self) -> &'static str {
+        "gemini"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn generate(&self, request: &GenerateRequest) -> anyhow::Result<String> {
+        let body = self.post("generateContent", &self.payload(request)).await?;
+        parse_generate_content_response(&body)
+    }
+
+    async fn count_tokens(&self, text: &str) -> anyhow::Result<Option<usize>> {
+        let body = self.post("countTokens", &json!({ "contents": [{ "role": "user", "parts": [{ "text": text }] }] })).await?;
+        let count = body["totalTokens"].as_u64()
+            .ok_or_else(|| anyhow::anyhow!("Gemini response has no totalTokens"))?;
+        Ok(Some(count as usize))
+    }
+
+    fn request_counters(&self) -> RequestCounters {
+        RequestCounters {
+            invalid_utf8: self.invalid_utf8.load(Ordering::Relaxed),
+            ..RequestCounters::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payload_detects_the_mime_type_of_each_image() {
+        let mut request = GenerateRequest::new("describe");
+        request.images = vec!["/9j/4AAQSkZJRg==".to_string(), "iVBORw0KGgo=".to_string()];
+
+        let payload = GeminiBackend::new("key", "gemini-1.5-flash").payload(&request);
+        let parts = &payload["contents"][0]["parts"];
+        assert_eq!(parts[1]["inline_data"]["mime_type"], "image/jpeg");
+        assert_eq!(parts[2]["inline_data"]["mime_type"], "image/png");
+    }
+
+    #[tokio::test]
+    async fn error_status_is_an_api_error_with_the_json_message() {
+        use crate::ai::is_transient;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            for body in [r#"{"error":{"code":429,"message":"Resource has been exhausted","status":"RESOURCE_EXHAUSTED"}}"#, "upstream connect error"] {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = [0u8; 4096];
+                let _ = stream.read(&mut request).await;
+                let status = if body.starts_with('{') { "429 Too Many Requests" } else { "503 Service Unavailable" };
+                let head = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", status, body.len());
+                stream.write_all(head.as_bytes()).await.unwrap();
+                stream.write_all(body.as_bytes()).await.unwrap();
+            }
+        });
+        let backend = GeminiBackend::new("key", "gemini-1.5-flash").with_url(&format!("http://{}", addr));
+
+        let error = backend.generate(&GenerateRequest::new("hi")).await.unwrap_err();
+        let api = error.downcast_ref::<ApiError>().expect("an ApiError");
+        assert_eq!(api.status.as_u16(), 429);
+        assert_eq!(api.body, "Resource has been exhausted");
+        assert!(!is_transient(&error));
+
+        let error = backend.generate(&GenerateRequest::new("hi")).await.unwrap_err();
+        let api = error.downcast_ref::<ApiError>().expect("an ApiError");
+        assert_eq!((api.status.as_u16(), api.body.as_str()), (503, "upstream connect error"));
+        assert!(is_transient(&error));
+    }
+}
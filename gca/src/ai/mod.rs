@@ -1,17 +1,475 @@
+#[cfg(feature = "anthropic")]
+pub mod anthropic;
+pub mod backend;
+pub mod embeddings;
+#[cfg(feature = "gemini")]
+pub mod gemini;
+
+use embeddings::EmbeddingCache;
+use crate::http::client_builder;
+use futures_util::{stream, Stream};
 use reqwest::Client;
+use serde::{Deserialize, Serialize};
 use serde_json::json;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Mutex;
 use std::time::Duration;
 
+/// One `{role, content}` chat message, e.g. a few-shot primer turn
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatTurn {
+    pub role: String,
+    pub content: String,
+}
+
+impl ChatTurn {
+    pub fn to_json(&self) -> serde_json::Value {
+        json!({ "role": self.role, "content": self.content })
+    }
+}
+
+/// Embedding model used when none is set explicitly
+pub const DEFAULT_EMBEDDING_MODEL: &str = "nomic-embed-text";
+
+/// Address of a local Ollama server
+pub const DEFAULT_OLLAMA_URL: &str = "http://localhost:11434";
+
+/// Variable the Ollama CLI reads the server address from
+pub const OLLAMA_HOST_ENV: &str = "OLLAMA_HOST";
+
+/// Server address from `OLLAMA_HOST`, or `DEFAULT_OLLAMA_URL` when unset.
+/// As in the Ollama CLI, `gpu-box` means `http://gpu-box:11434`
+pub fn default_ollama_url() -> String {
+    std::env::var(OLLAMA_HOST_ENV).ok()
+        .map(|host| ollama_host_url(&host))
+        .filter(|url| !url.is_empty())
+        .unwrap_or_else(|| DEFAULT_OLLAMA_URL.to_string())
+}
+
+/// URL for an `OLLAMA_HOST` value: `http://` is added when the scheme is
+/// missing, and then port 11434 when the port is missing too
+pub fn ollama_host_url(host: &str) -> String {
+    let host = host.trim().trim_end_matches('/');
+    if host.is_empty() || host.contains("://") {
+        return host.to_string();
+    }
+    let authority = host.split('/').next().unwrap_or(host);
+    // Порт - цифры после последнего ':'; у "[::1]" там "1]", это не порт
+    let has_port = authority.rsplit_once(':')
+        .is_some_and(|(_, port)| !port.is_empty() && port.bytes().all(|b| b.is_ascii_digit()));
+    if has_port {
+        format!("http://{}", host)
+    } else {
+        let (authority, path) = host.split_at(authority.len());
+        format!("http://{}:11434{}", authority, path)
+    }
+}
+
+/// Address of a local OpenAI-compatible server (vLLM's default port)
+pub const DEFAULT_OPENAI_URL: &str = "http://localhost:8000";
+
+/// Bearer token for servers behind an authenticating proxy, used when no
+/// key is set with `Ollama::with_api_key`
+pub const API_KEY_ENV: &str = "APET_API_KEY";
+
+/// Key from `API_KEY_ENV`, if set and not blank
+pub fn api_key_from_env() -> Option<String> {
+    std::env::var(API_KEY_ENV).ok()
+        .map(|key| key.trim().to_string())
+        .filter(|key| !key.is_empty())
+}
+
+/// Warm-up latency above which the model is reported as very slow
+pub const SLOW_MODEL_LATENCY: Duration = Duration::from_secs(20);
+/// Request timeout as a multiple of the warm-up latency
+const WARMUP_TIMEOUT_FACTOR: u32 = 3;
+/// Upper bound for both the warm-up request and the scaled timeout
+const MAX_WARMUP_TIMEOUT: Duration = Duration::from_secs(300);
+/// Throwaway request that makes Ollama load the model
+const WARMUP_PROMPT: &str = "Reply with OK.";
+
+/// Appended to the prompt when the model's first answer was empty
+const EMPTY_RETRY_INSTRUCTION: &str = "Respond with the prompt text only; do not return an empty message.";
+
+/// Sent after a cut-off answer under `TruncationPolicy::Continue`
+const CONTINUE_INSTRUCTION: &str = "Continue exactly where you stopped. Return only the rest of the text.";
+/// Follow-up requests allowed for one cut-off answer
+pub const MAX_CONTINUATIONS: usize = 2;
+
+/// Wait before the first retry of a transient failure; doubles per attempt
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+/// Longest wait between two attempts, before jitter
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(8);
+
+/// A pull fails when the server sends nothing for this long
+const PULL_STALL_TIMEOUT: Duration = Duration::from_secs(300);
+/// Upper bound for a whole pull, however steadily it progresses
+const MAX_PULL_DURATION: Duration = Duration::from_secs(4 * 3600);
+/// A streamed reply fails when no data arrives for this long after the
+/// first byte
+const STREAM_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+/// Upper bound for a whole streamed reply, however steadily it arrives
+const MAX_STREAM_DURATION: Duration = Duration::from_secs(3600);
+
+/// What to do when Ollama stops at `num_predict` (`done_reason: "length"`)
+/// and the text ends mid-sentence
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TruncationPolicy {
+    /// Use the cut-off text as is
+    Keep,
+    /// Fail with `TruncatedResponse`, so the fragment never becomes a prompt
+    #[default]
+    Reject,
+    /// Ask the model to go on, up to `MAX_CONTINUATIONS` times, then reject
+    Continue,
+}
+
+impl TruncationPolicy {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.trim().to_lowercase().as_str() {
+            "keep" => Ok(TruncationPolicy::Keep),
+            "reject" => Ok(TruncationPolicy::Reject),
+            "continue" => Ok(TruncationPolicy::Continue),
+            other => Err(format!("unknown truncation policy '{}' (keep, reject, continue)", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for TruncationPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            TruncationPolicy::Keep => "keep",
+            TruncationPolicy::Reject => "reject",
+            TruncationPolicy::Continue => "continue",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The answer was cut off at `num_predict` and the policy refused it
+#[derive(Debug, Clone)]
+pub struct TruncatedResponse {
+    pub partial: String,
+}
+
+impl std::fmt::Display for TruncatedResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Ollama response was truncated at num_predict ({} chars)", self.partial.chars().count())
+    }
+}
+
+impl std::error::Error for TruncatedResponse {}
+
+/// Whether a chat response stopped because it hit the token limit
+pub fn is_truncated(response: &serde_json::Value) -> bool {
+    response["done_reason"].as_str() == Some("length")
+}
+
+/// `payload` extended with the cut-off answer and a request to go on
+pub fn continuation_payload(payload: &serde_json::Value, partial: &str) -> serde_json::Value {
+    let mut next = payload.clone();
+    if let Some(messages) = next["messages"].as_array_mut() {
+        messages.push(json!({ "role": "assistant", "content": partial }));
+        messages.push(json!({ "role": "user", "content": CONTINUE_INSTRUCTION }));
+    }
+    next
+}
+
+/// Appends a continuation to the text so far; both are trimmed, so they
+/// are joined with a space unless the continuation starts with punctuation
+pub fn join_continuation(text: &str, rest: &str) -> String {
+    if rest.starts_with(|c: char| c.is_ascii_punctuation()) {
+        format!("{}{}", text, rest)
+    } else {
+        format!("{} {}", text, rest)
+    }
+}
+
+/// Wire format of the server an `Ollama` client talks to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backend {
+    /// Ollama's own API: `/api/chat`, `/api/tags`, ...
+    #[default]
+    Ollama,
+    /// OpenAI's `/v1/chat/completions`, as served by vLLM or LM Studio
+    OpenAiCompatible,
+}
+
+impl Backend {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.trim().to_lowercase().as_str() {
+            "ollama" => Ok(Backend::Ollama),
+            "openai" => Ok(Backend::OpenAiCompatible),
+            other => Err(format!("unknown backend '{}' (ollama, openai)", other)),
+        }
+    }
+    
+    /// Name used in errors and logs
+    pub fn label(&self) -> &'static str {
+        match self {
+            Backend::Ollama => "Ollama",
+            Backend::OpenAiCompatible => "OpenAI-compatible",
+        }
+    }
+    
+    fn chat_path(&self) -> &'static str {
+        match self {
+            Backend::Ollama => "/api/chat",
+            Backend::OpenAiCompatible => "/v1/chat/completions",
+        }
+    }
+    
+    /// Reply text of a non-streamed chat response and whether it stopped
+    /// at the token limit
+    pub fn reply(&self, response: &serde_json::Value) -> (String, bool) {
+        let (message, truncated) = match self {
+            Backend::Ollama => (&response["message"], is_truncated(response)),
+            Backend::OpenAiCompatible => {
+                let choice = &response["choices"][0];
+                (&choice["message"], choice["finish_reason"].as_str() == Some("length"))
+            }
+        };
+        (message["content"].as_str().unwrap_or_default().trim().to_string(), truncated)
+    }
+    
+    /// Content delta of one line of a streamed chat response and whether
+    /// the stream is done
+    pub fn parse_stream_line(&self, line: &str) -> anyhow::Result<(String, bool)> {
+        match self {
+            Backend::Ollama => parse_stream_chunk(line),
+            Backend::OpenAiCompatible => parse_sse_chunk(line),
+        }
+    }
+}
+
+impl std::fmt::Display for Backend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Backend::Ollama => "ollama",
+            Backend::OpenAiCompatible => "openai",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// `payload` in the `/api/chat` shape rewritten for `/v1/chat/completions`:
+/// `temperature` and `num_predict` move from `options` to the top level
+/// (`max_tokens`), and a message's base64 `images` become `image_url`
+/// content parts next to its text
+pub fn openai_payload(payload: &serde_json::Value) -> serde_json::Value {
+    let messages: Vec<serde_json::Value> = payload["messages"].as_array().into_iter().flatten()
+        .map(|message| match message["images"].as_array() {
+            Some(images) if !images.is_empty() => {
+                let mut parts = vec![json!({ "type": "text", "text": message["content"] })];
+                parts.extend(images.iter().filter_map(|image| image.as_str()).map(|image| json!({
+                    "type": "image_url",
+                    "image_url": { "url": image_data_url(image) }
+                })));
+                json!({ "role": message["role"], "content": parts })
+            }
+            _ => json!({ "role": message["role"], "content": message["content"] }),
+        })
+        .collect();
+    
+    let mut body = json!({
+        "model": payload["model"],
+        "messages": messages,
+        "stream": payload["stream"].as_bool().unwrap_or(false)
+    });
+    if let Some(temperature) = payload["options"].get("temperature") {
+        body["temperature"] = temperature.clone();
+    }
+    if let Some(tokens) = payload["options"].get("num_predict") {
+        body["max_tokens"] = tokens.clone();
+    }
+    body
+}
+
+/// Mime type of a base64 image; JPEG and PNG are told apart by their header
+pub(crate) fn image_mime_type(base64: &str) -> &'static str {
+    if base64.starts_with("/9j/") { "image/jpeg" } else { "image/png" }
+}
+
+/// Data URL of a base64 image
+fn image_data_url(base64: &str) -> String {
+    format!("data:{};base64,{}", image_mime_type(base64), base64)
+}
+
+/// The server answered a request with an error status
+#[derive(Debug, Clone)]
+pub struct ApiError {
+    /// Name of the API, e.g. `Backend::label`
+    pub backend: &'static str,
+    pub status: reqwest::StatusCode,
+    /// Response body, with the API key masked
+    pub body: String,
+}
+
+impl ApiError {
+    /// Error of an API that answers `{"error": {"message": ...}}`, as the
+    /// Anthropic and Gemini APIs do; keeps the whole body when it is not
+    /// JSON of that shape, e.g. a proxy's HTML page
+    pub fn with_error_message(backend: &'static str, status: reqwest::StatusCode, body: String) -> Self {
+        let message = serde_json::from_str::<serde_json::Value>(&body).ok()
+            .and_then(|json| json["error"]["message"].as_str().map(str::to_string));
+        Self { backend, status, body: message.unwrap_or(body) }
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} API error {}: {}", self.backend, self.status, self.body)
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+/// The server answered `/api/tags` with an empty `models` list, as a fresh
+/// Ollama install does
+#[derive(Debug, Clone)]
+pub struct NoModelsInstalled {
+    pub url: String,
+}
+
+impl std::fmt::Display for NoModelsInstalled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Ollama at {} has no models installed; pull one with `ollama pull <model>`", self.url)
+    }
+}
+
+impl std::error::Error for NoModelsInstalled {}
+
+/// Whether `error` may go away on its own: a network failure (refused or
+/// reset connection, timeout) or a 5xx answer. A 4xx, an empty answer or
+/// an unparsable body would fail the same way again.
+pub fn is_transient(error: &anyhow::Error) -> bool {
+    if let Some(api) = error.downcast_ref::<ApiError>() {
+        return api.status.is_server_error();
+    }
+    error.downcast_ref::<reqwest::Error>()
+        .is_some_and(|e| e.is_connect() || e.is_timeout() || e.is_request() || e.is_body())
+}
+
+/// Wait before retry number `attempt` (0-based): 500ms, 1s, 2s, ... up to
+/// 8s, plus up to a quarter more at random so parallel workers spread out
+pub fn retry_delay(attempt: usize) -> Duration {
+    let delay = RETRY_BASE_DELAY.saturating_mul(1 << attempt.min(16)).min(MAX_RETRY_DELAY);
+    delay + delay.mul_f64(rand::random::<f64>() * 0.25)
+}
+
+/// The model answered with nothing but whitespace
+#[derive(Debug, Clone, Copy)]
+pub struct EmptyResponse;
+
+impl std::fmt::Display for EmptyResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Ollama returned empty response")
+    }
+}
+
+impl std::error::Error for EmptyResponse {}
+
+/// Cap on retries shared by all requests of one generation, so a flaky
+/// server cannot stretch a run without bound. Unlimited by default.
+#[derive(Debug, Default)]
+pub struct RetryBudget {
+    limit: Option<usize>,
+    used: AtomicUsize,
+    /// Retries skipped because the budget was spent
+    denied: AtomicUsize,
+}
+
+impl RetryBudget {
+    pub fn new(limit: Option<usize>) -> Self {
+        Self { limit, ..Default::default() }
+    }
+    
+    /// Takes one retry from the budget; `false` once it is spent
+    pub fn try_acquire(&self) -> bool {
+        let acquired = self.used.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+            match self.limit {
+                Some(limit) if used >= limit => None,
+                _ => Some(used + 1),
+            }
+        }).is_ok();
+        if !acquired {
+            self.denied.fetch_add(1, Ordering::Relaxed);
+        }
+        acquired
+    }
+    
+    /// Starts a new generation with the full budget
+    pub fn reset(&self) {
+        self.used.store(0, Ordering::Relaxed);
+        self.denied.store(0, Ordering::Relaxed);
+    }
+    
+    pub fn limit(&self) -> Option<usize> {
+        self.limit
+    }
+    
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::Relaxed)
+    }
+    
+    pub fn denied(&self) -> usize {
+        self.denied.load(Ordering::Relaxed)
+    }
+}
+
+/// Outcome of the warm-up request sent before a run
+#[derive(Debug, Clone, Copy)]
+pub struct WarmUp {
+    pub latency: Duration,
+    /// Per-request timeout in effect after the warm-up
+    pub timeout: Duration,
+}
+
+impl WarmUp {
+    pub fn is_slow(&self) -> bool {
+        self.latency >= SLOW_MODEL_LATENCY
+    }
+}
+
+/// Timeout for a model whose cold request took `latency`: a few times the
+/// latency, never below `base` and never above five minutes
+pub fn scaled_timeout(latency: Duration, base: Duration) -> Duration {
+    (latency * WARMUP_TIMEOUT_FACTOR).min(MAX_WARMUP_TIMEOUT).max(base)
+}
+
 pub struct Ollama {
     client: Client,
     url: String,
     model: String,
     timeout: Duration,
+    payload_overlay: Option<serde_json::Value>,
+    embedding_model: String,
+    embedding_cache: Option<Mutex<EmbeddingCache>>,
+    primer: Vec<ChatTurn>,
+    /// Whether an empty answer gets one reworded retry
+    retry_empty: bool,
+    /// Requests repeated because the first answer was empty
+    empty_retries: AtomicUsize,
+    truncation: TruncationPolicy,
+    /// Answers that hit `num_predict`, whatever the policy did with them
+    truncated_responses: AtomicUsize,
+    retry_budget: RetryBudget,
+    backend: Backend,
+    /// Sent as a bearer token with every request; never shown in errors
+    api_key: Option<String>,
+    /// Attempts after the first for a chat request failing transiently
+    max_retries: usize,
+    /// Chat requests repeated after a network error or 5xx answer
+    transient_retries: AtomicUsize,
+    /// Invalid UTF-8 sequences replaced in response bodies
+    invalid_utf8: AtomicUsize,
 }
 
 impl Ollama {
     pub fn new(url: &str, model: &str) -> Self {
-        let client = Client::builder()
+        let client = client_builder()
             .timeout(Duration::from_secs(30))
             .build()
             .unwrap();
@@ -21,16 +479,241 @@ impl Ollama {
             url: url.into(), 
             model: model.into(),
             timeout: Duration::from_secs(15),
+            payload_overlay: None,
+            embedding_model: DEFAULT_EMBEDDING_MODEL.to_string(),
+            embedding_cache: None,
+            primer: Vec::new(),
+            retry_empty: false,
+            empty_retries: AtomicUsize::new(0),
+            truncation: TruncationPolicy::default(),
+            truncated_responses: AtomicUsize::new(0),
+            retry_budget: RetryBudget::default(),
+            backend: Backend::default(),
+            api_key: api_key_from_env(),
+            max_retries: 0,
+            transient_retries: AtomicUsize::new(0),
+            invalid_utf8: AtomicUsize::new(0),
         }
     }
     
+    /// Retries chat requests up to `max` times after a network error or a
+    /// 5xx answer, waiting `retry_delay` between attempts. Every retry also
+    /// takes one from the retry budget. Other failures are returned at once.
+    pub fn with_retries(mut self, max: usize) -> Self {
+        self.max_retries = max;
+        self
+    }
+    
+    pub fn max_retries(&self) -> usize {
+        self.max_retries
+    }
+    
+    /// Repeats a request once, with an instruction not to answer empty,
+    /// when the model's answer was empty. Off by default: an empty answer
+    /// is not transient and fails at once.
+    pub fn with_empty_retry(mut self, enabled: bool) -> Self {
+        self.retry_empty = enabled;
+        self
+    }
+    
+    /// Talks to an OpenAI-compatible server instead of Ollama; `generate`,
+    /// `chat` and the other methods keep their signatures. The URL is the
+    /// server's base, with or without the trailing `/v1`.
+    pub fn with_backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+    
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+    
+    /// Sends `Authorization: Bearer <key>` with every request, for hosted
+    /// OpenAI-compatible servers or Ollama behind an authenticating proxy.
+    /// Replaces the key read from `APET_API_KEY`.
+    pub fn with_api_key(mut self, key: &str) -> Self {
+        self.api_key = Some(key.to_string());
+        self
+    }
+    
+    /// Full URL of `path` on the server
+    fn endpoint(&self, path: &str) -> String {
+        let base = self.url.trim_end_matches('/');
+        match self.backend {
+            Backend::Ollama => format!("{}{}", base, path),
+            // Базовый адрес OpenAI часто дают вместе с /v1
+            Backend::OpenAiCompatible => format!("{}{}", base.trim_end_matches("/v1"), path),
+        }
+    }
+    
+    fn authorized(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.api_key {
+            Some(key) => request.bearer_auth(key),
+            None => request,
+        }
+    }
+    
+    /// Body of a failed response for the error message, with the API key
+    /// masked in case the server echoes the request headers
+    async fn error_body(&self, response: reqwest::Response) -> String {
+        let body = response.text().await.unwrap_or_default();
+        match &self.api_key {
+            Some(key) => body.replace(key.as_str(), "***"),
+            None => body,
+        }
+    }
+    
+    /// `payload` (in the `/api/chat` shape) as this backend expects it,
+    /// with the payload overlay applied last
+    fn wire_payload(&self, payload: serde_json::Value) -> serde_json::Value {
+        let mut payload = match self.backend {
+            Backend::Ollama => payload,
+            Backend::OpenAiCompatible => openai_payload(&payload),
+        };
+        if let Some(overlay) = &self.payload_overlay {
+            merge_json(&mut payload, overlay);
+        }
+        payload
+    }
+    
     pub fn with_timeout(mut self, timeout: Duration) -> Self {
         self.timeout = timeout;
         self
     }
     
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+    
+    /// Sends one throwaway request so the model is loaded before the run,
+    /// then raises the per-request timeout to fit the measured latency
+    pub async fn warm_up(&mut self) -> anyhow::Result<WarmUp> {
+        let payload = self.chat_payload(WARMUP_PROMPT, Some(0.0), Some(4), None, &[]);
+        let started = std::time::Instant::now();
+        self.send_chat(payload, MAX_WARMUP_TIMEOUT).await?;
+        let latency = started.elapsed();
+        
+        self.timeout = scaled_timeout(latency, self.timeout);
+        Ok(WarmUp { latency, timeout: self.timeout })
+    }
+    
+    /// Extra fields deep-merged into every chat request body, for gateways
+    /// that expect e.g. a `user` id or custom `options`. Keys present in
+    /// both the generated payload and the overlay take the overlay's value.
+    pub fn with_payload_overlay(mut self, overlay: serde_json::Value) -> Self {
+        self.payload_overlay = Some(overlay);
+        self
+    }
+    
+    /// Turns inserted before the user message of every chat request
+    /// (after the system message), as in-context examples
+    pub fn with_primer(mut self, turns: Vec<ChatTurn>) -> Self {
+        self.primer = turns;
+        self
+    }
+    
+    /// How cut-off generations are handled (`Reject` by default)
+    pub fn with_truncation_policy(mut self, policy: TruncationPolicy) -> Self {
+        self.truncation = policy;
+        self
+    }
+    
+    pub fn truncation_policy(&self) -> TruncationPolicy {
+        self.truncation
+    }
+    
+    /// Most retries allowed between two `retry_budget().reset()` calls;
+    /// `MapElites::evolve` resets it every generation
+    pub fn with_retry_budget(mut self, limit: usize) -> Self {
+        self.retry_budget = RetryBudget::new(Some(limit));
+        self
+    }
+    
+    pub fn retry_budget(&self) -> &RetryBudget {
+        &self.retry_budget
+    }
+    
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+    
+    /// Whether requests carry primer turns
+    pub fn is_primed(&self) -> bool {
+        !self.primer.is_empty()
+    }
+    
+    pub fn with_embedding_model(mut self, model: &str) -> Self {
+        self.embedding_model = model.to_string();
+        self
+    }
+    
+    /// Consult `cache` before calling the embeddings API
+    pub fn with_embedding_cache(mut self, cache: EmbeddingCache) -> Self {
+        self.embedding_cache = Some(Mutex::new(cache));
+        self
+    }
+    
     pub async fn generate(&self, prompt: &str) -> anyhow::Result<String> {
-        self.generate_with_options(prompt, None, None, None).await
+        self.generate_with_options(prompt, None, None, None, &[]).await
+    }
+    
+    /// Like `generate`, but yields the reply as it is produced: one item
+    /// per non-empty `message.content` delta of the streamed `/api/chat`
+    /// response, ending after the chunk marked `done` (for an
+    /// OpenAI-compatible backend, the `delta` of each server-sent event up
+    /// to `[DONE]`). The items concatenated
+    /// are the untrimmed reply; empty-answer retries and the truncation
+    /// policy do not apply.
+    pub fn generate_stream(&self, prompt: &str) -> impl Stream<Item = anyhow::Result<String>> + '_ {
+        let mut payload = self.chat_payload(prompt, None, None, None, &[]);
+        payload["stream"] = json!(true);
+        let payload = self.wire_payload(payload);
+        
+        stream::unfold(StreamState::Start(payload), move |state| async move {
+            let mut chat = match state {
+                StreamState::Start(payload) => match self.open_chat_stream(&payload).await {
+                    Ok(response) => ChatStream::new(response, self.backend, STREAM_IDLE_TIMEOUT),
+                    Err(e) => return Some((Err(e), StreamState::Done)),
+                },
+                StreamState::Reading(chat) => chat,
+                StreamState::Done => return None,
+            };
+            // Пустые дельты (обычно финальный чанк) не отдаются
+            loop {
+                match chat.next_chunk().await {
+                    Ok((content, done)) if content.is_empty() => {
+                        if done {
+                            return None;
+                        }
+                    }
+                    Ok((content, done)) => {
+                        let next = if done { StreamState::Done } else { StreamState::Reading(chat) };
+                        return Some((Ok(content), next));
+                    }
+                    Err(e) => return Some((Err(e), StreamState::Done)),
+                }
+            }
+        })
+    }
+    
+    /// Sends a streamed chat request. `timeout` bounds only the wait for
+    /// the response headers (connecting and the first byte); a long reply
+    /// is then limited by `STREAM_IDLE_TIMEOUT` between chunks.
+    async fn open_chat_stream(&self, payload: &serde_json::Value) -> anyhow::Result<reqwest::Response> {
+        // Таймаут запроса reqwest покрывает и тело, поэтому его поднимаем до общего предела
+        let request = self.authorized(self.client.post(self.endpoint(self.backend.chat_path())))
+            .json(payload)
+            .timeout(MAX_STREAM_DURATION)
+            .send();
+        let response = tokio::time::timeout(self.timeout, request).await
+            .map_err(|_| anyhow::anyhow!("{} sent no response within {}s", self.backend.label(), self.timeout.as_secs()))??;
+        
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = self.error_body(response).await;
+            return Err(ApiError { backend: self.backend.label(), status, body }.into());
+        }
+        Ok(response)
     }
     
     pub async fn generate_with_options(
@@ -38,14 +721,94 @@ impl Ollama {
         prompt: &str, 
         temperature: Option<f64>,
         max_tokens: Option<usize>,
-        system: Option<&str>
+        system: Option<&str>,
+        images: &[String]
     ) -> anyhow::Result<String> {
+        let payload = self.chat_payload(prompt, temperature, max_tokens, system, images);
+        match self.complete(payload).await {
+            // Бюджет исчерпан - сбой записывается без повтора
+            Err(e) if self.retry_empty && e.is::<EmptyResponse>() && self.retry_budget.try_acquire() => {
+                // Пустой ответ обычно случайный сбой формата - один повтор с уточнённой инструкцией
+                self.empty_retries.fetch_add(1, Ordering::Relaxed);
+                let reformatted = format!("{}\n\n{}", prompt, EMPTY_RETRY_INSTRUCTION);
+                let payload = self.chat_payload(&reformatted, temperature, max_tokens, system, images);
+                self.complete(payload).await
+            }
+            result => result,
+        }
+    }
+    
+    /// Sends a generation request and applies the truncation policy
+    async fn complete(&self, payload: serde_json::Value) -> anyhow::Result<String> {
+        let (mut text, truncated) = self.send_chat_checked(payload.clone(), self.timeout).await?;
+        if !truncated || self.truncation == TruncationPolicy::Keep {
+            return Ok(text);
+        }
+        self.truncated_responses.fetch_add(1, Ordering::Relaxed);
+        
+        if self.truncation == TruncationPolicy::Continue {
+            for _ in 0..MAX_CONTINUATIONS {
+                let (rest, still_truncated) = self.send_chat_checked(continuation_payload(&payload, &text), self.timeout).await?;
+                text = join_continuation(&text, &rest);
+                if !still_truncated {
+                    return Ok(text);
+                }
+            }
+        }
+        Err(TruncatedResponse { partial: text }.into())
+    }
+    
+    /// Total requests retried after an empty answer
+    pub fn empty_retries(&self) -> usize {
+        self.empty_retries.load(Ordering::Relaxed)
+    }
+    
+    /// Total chat requests retried after a transient failure
+    pub fn transient_retries(&self) -> usize {
+        self.transient_retries.load(Ordering::Relaxed)
+    }
+    
+    /// Total generations that stopped at `num_predict`
+    pub fn truncated_responses(&self) -> usize {
+        self.truncated_responses.load(Ordering::Relaxed)
+    }
+    
+    /// Total invalid UTF-8 sequences replaced in response bodies
+    pub fn invalid_utf8(&self) -> usize {
+        self.invalid_utf8.load(Ordering::Relaxed)
+    }
+    
+    /// Request body for `/api/chat`. Ollama ignores a top-level `system`
+    /// key, so the system prompt goes first in `messages`, then the primer.
+    /// `images` (base64) are attached to the user turn for vision models.
+    pub fn chat_payload(
+        &self,
+        prompt: &str,
+        temperature: Option<f64>,
+        max_tokens: Option<usize>,
+        system: Option<&str>,
+        images: &[String]
+    ) -> serde_json::Value {
+        let mut messages = Vec::new();
+        if let Some(sys) = system {
+            messages.push(json!({
+                "role": "system",
+                "content": sys
+            }));
+        }
+        messages.extend(self.primer.iter().map(ChatTurn::to_json));
+        let mut user = json!({
+            "role": "user",
+            "content": prompt
+        });
+        if !images.is_empty() {
+            user["images"] = json!(images);
+        }
+        messages.push(user);
+        
         let mut payload = json!({
             "model": self.model,
-            "messages": [{
-                "role": "user",
-                "content": prompt
-            }],
+            "messages": messages,
             "stream": false
         });
         
@@ -62,36 +825,67 @@ impl Ollama {
             payload["options"]["num_predict"] = json!(tokens);
         }
         
-        if let Some(sys) = system {
-            payload["system"] = json!(sys);
-        }
+        payload
+    }
+    
+    /// Sends a prepared conversation, e.g. `AdversarialPrompt::to_messages`
+    /// with an injected system message, and returns the reply text
+    pub async fn chat(&self, mut messages: Vec<serde_json::Value>) -> anyhow::Result<String> {
+        let at = messages.iter().take_while(|m| m["role"] == "system").count();
+        messages.splice(at..at, self.primer.iter().map(ChatTurn::to_json));
         
-        let response = self.client
-            .post(format!("{}/api/chat", self.url))
-            .json(&payload)
-            .timeout(self.timeout)
+        let payload = json!({
+            "model": self.model,
+            "messages": messages,
+            "stream": false
+        });
+        
+        self.send_chat(payload, self.timeout).await
+    }
+    
+    async fn send_chat(&self, payload: serde_json::Value, timeout: Duration) -> anyhow::Result<String> {
+        self.send_chat_checked(payload, timeout).await.map(|(text, _)| text)
+    }
+    
+    /// Reply text and whether it was cut off at `num_predict`, retried per
+    /// `with_retries`
+    async fn send_chat_checked(&self, payload: serde_json::Value, timeout: Duration) -> anyhow::Result<(String, bool)> {
+        let payload = self.wire_payload(payload);
+        let mut attempt = 0;
+        loop {
+            match self.send_chat_once(&payload, timeout).await {
+                // Бюджет исчерпан - сбой возвращается без повтора
+                Err(e) if attempt < self.max_retries && is_transient(&e) && self.retry_budget.try_acquire() => {
+                    self.transient_retries.fetch_add(1, Ordering::Relaxed);
+                    tokio::time::sleep(retry_delay(attempt)).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+    
+    async fn send_chat_once(&self, payload: &serde_json::Value, timeout: Duration) -> anyhow::Result<(String, bool)> {
+        let response = self.authorized(self.client.post(self.endpoint(self.backend.chat_path())))
+            .json(payload)
+            .timeout(timeout)
             .send()
             .await?;
         
         if !response.status().is_success() {
             let status = response.status();
-            let error_text = response.text().await.unwrap_or_default();
-            anyhow::bail!("Ollama API error {}: {}", status, error_text);
+            let body = self.error_body(response).await;
+            return Err(ApiError { backend: self.backend.label(), status, body }.into());
         }
         
-        let response_json: serde_json::Value = response.json().await?;
-        
-        let text = response_json["message"]["content"]
-            .as_str()
-            .unwrap_or_default()
-            .trim()
-            .to_string();
+        let response_json = parse_chat_response(&read_text(response, &self.invalid_utf8).await?)?;
+        let (text, truncated) = self.backend.reply(&response_json);
         
         if text.is_empty() {
-            anyhow::bail!("Ollama returned empty response");
+            return Err(EmptyResponse.into());
         }
         
-        Ok(text)
+        Ok((text, truncated))
     }
     
     pub async fn generate_adversarial(
@@ -123,20 +917,130 @@ impl Ollama {
             attack_goal, target_system, complexity
         );
         
-        self.generate_with_options(&prompt, Some(0.8), Some(200), Some(&system_prompt)).await
+        self.generate_with_options(&prompt, Some(0.8), Some(200), Some(&system_prompt), &[]).await
     }
     
+    /// Embedding vector of `text`, served from the cache when possible
+    pub async fn embed(&self, text: &str) -> anyhow::Result<Vec<f32>> {
+        if let Some(cache) = &self.embedding_cache {
+            let mut cache = cache.lock().map_err(|_| anyhow::anyhow!("Embedding cache lock poisoned"))?;
+            // Векторы другой модели несовместимы - сбрасываем кэш целиком
+            if cache.model() != self.embedding_model {
+                cache.reset(&self.embedding_model)?;
+            }
+            if let Some(embedding) = cache.get(text)? {
+                return Ok(embedding);
+            }
+        }
+        
+        let (path, payload) = match self.backend {
+            Backend::Ollama => ("/api/embeddings", json!({ "model": self.embedding_model, "prompt": text })),
+            Backend::OpenAiCompatible => ("/v1/embeddings", json!({ "model": self.embedding_model, "input": text })),
+        };
+        
+        let response = self.authorized(self.client.post(self.endpoint(path)))
+            .json(&payload)
+            .timeout(self.timeout)
+            .send()
+            .await?;
+        
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = self.error_body(response).await;
+            anyhow::bail!("Embeddings request failed {}: {}", status, error_text);
+        }
+        
+        let response_json: serde_json::Value = serde_json::from_str(&read_text(response, &self.invalid_utf8).await?)?;
+        let embedding = match self.backend {
+            Backend::Ollama => &response_json["embedding"],
+            Backend::OpenAiCompatible => &response_json["data"][0]["embedding"],
+        };
+        let embedding: Vec<f32> = embedding
+            .as_array()
+            .ok_or_else(|| anyhow::anyhow!("No embedding in response"))?
+            .iter()
+            .filter_map(|v| v.as_f64().map(|f| f as f32))
+            .collect();
+        
+        if let Some(cache) = &self.embedding_cache {
+            let mut cache = cache.lock().map_err(|_| anyhow::anyhow!("Embedding cache lock poisoned"))?;
+            cache.insert(text, embedding.clone())?;
+        }
+        
+        Ok(embedding)
+    }
+    
+    /// Number of tokens the model's own tokenizer makes of `text`. Ollama
+    /// has no tokenize endpoint, so this runs a raw one-token generation and
+    /// reads `prompt_eval_count`; the count may include a BOS token.
+    pub async fn count_tokens(&self, text: &str) -> anyhow::Result<usize> {
+        if self.backend != Backend::Ollama {
+            anyhow::bail!("Token counting needs the Ollama backend");
+        }
+        let payload = json!({
+            "model": self.model,
+            "prompt": text,
+            "raw": true,
+            "stream": false,
+            // Кэш контекста занизил бы prompt_eval_count у повторного запроса
+            "keep_alive": 0,
+            "options": { "num_predict": 1 }
+        });
+        
+        let response = self.authorized(self.client.post(self.endpoint("/api/generate")))
+            .json(&payload)
+            .timeout(self.timeout)
+            .send()
+            .await?;
+        
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = self.error_body(response).await;
+            anyhow::bail!("Token count request failed {}: {}", status, error_text);
+        }
+        
+        let response_json: serde_json::Value = serde_json::from_str(&read_text(response, &self.invalid_utf8).await?)?;
+        response_json["prompt_eval_count"]
+            .as_u64()
+            .map(|count| count as usize)
+            .ok_or_else(|| anyhow::anyhow!("No prompt_eval_count in response"))
+    }
+    
+    /// Whether the model API answers; see `check_health` for the details
     pub async fn check_connection(&self) -> anyhow::Result<bool> {
-        let response = self.client
-            .head(&self.url)
+        Ok(self.check_health().await.model_api_ok)
+    }
+    
+    /// Probes the model list (`/api/tags`, or `/v1/models` for OpenAI
+    /// servers) with a 5 s timeout. Unlike a request to the base URL this
+    /// tells a stopped server from one whose model API is broken
+    pub async fn check_health(&self) -> ConnectionStatus {
+        let path = match self.backend {
+            Backend::Ollama => "/api/tags",
+            Backend::OpenAiCompatible => "/v1/models",
+        };
+        let response = match self.authorized(self.client.get(self.endpoint(path)))
             .timeout(Duration::from_secs(5))
             .send()
-            .await?;
+            .await
+        {
+            Ok(response) => response,
+            Err(_) => return ConnectionStatus::UNREACHABLE,
+        };
+        if !response.status().is_success() {
+            return ConnectionStatus::answered(None);
+        }
         
-        Ok(response.status().is_success())
+        let body = read_text(response, &self.invalid_utf8).await
+            .and_then(|body| Ok(serde_json::from_str::<serde_json::Value>(&body)?));
+        let models_count = body.ok().and_then(|body| match self.backend {
+            Backend::Ollama => parse_model_list(&body).ok().map(|models| models.len()),
+            Backend::OpenAiCompatible => body["data"].as_array().map(Vec::len),
+        });
+        ConnectionStatus::answered(models_count)
     }
     
-    async fn test_model(&self) -> anyhow::Result<()> {
+    pub async fn test_model(&self) -> anyhow::Result<()> {
         let payload = json!({
             "model": self.model,
             "messages": [{
@@ -146,16 +1050,15 @@ impl Ollama {
             "stream": false
         });
         
-        let response = self.client
-            .post(format!("{}/api/chat", self.url))
-            .json(&payload)
+        let response = self.authorized(self.client.post(self.endpoint(self.backend.chat_path())))
+            .json(&self.wire_payload(payload))
             .timeout(Duration::from_secs(10))
             .send()
             .await?;
         
         if !response.status().is_success() {
             let status = response.status();
-            let error_text = response.text().await.unwrap_or_default();
+            let error_text = self.error_body(response).await;
             anyhow::bail!("Model test failed {}: {}", status, error_text);
         }
         
@@ -163,8 +1066,15 @@ impl Ollama {
     }
     
     pub async fn list_models(&self) -> anyhow::Result<Vec<String>> {
-        let response = self.client
-            .get(format!("{}/api/tags", self.url))
+        Ok(self.list_models_detailed().await?.into_iter().map(|model| model.name).collect())
+    }
+    
+    /// Models on the server with their size, family and format
+    pub async fn list_models_detailed(&self) -> anyhow::Result<Vec<ModelInfo>> {
+        if self.backend == Backend::OpenAiCompatible {
+            return self.list_openai_models().await;
+        }
+        let response = self.authorized(self.client.get(self.endpoint("/api/tags")))
             .timeout(Duration::from_secs(10))
             .send()
             .await?;
@@ -173,51 +1083,273 @@ impl Ollama {
             anyhow::bail!("Failed to get models: {}", response.status());
         }
         
-        let response_json: serde_json::Value = response.json().await?;
-        
-        let models = response_json["models"]
-            .as_array()
-            .unwrap_or(&vec![])
-            .iter()
-            .filter_map(|m| m["name"].as_str().map(|s| s.to_string()))
-            .collect();
+        let response_json: serde_json::Value = serde_json::from_str(&read_text(response, &self.invalid_utf8).await?)?;
+        let models = parse_model_list(&response_json)
+            .map_err(|e| anyhow::anyhow!("{}: {}", self.endpoint("/api/tags"), e))?;
+        if models.is_empty() {
+            return Err(NoModelsInstalled { url: self.url.clone() }.into());
+        }
         
         Ok(models)
     }
     
-    pub async fn ensure_model(&self, model_name: &str) -> anyhow::Result<()> {
-        let models = self.list_models().await?;
+    /// Models from `/v1/models`; the API reports no size or format
+    async fn list_openai_models(&self) -> anyhow::Result<Vec<ModelInfo>> {
+        let response = self.authorized(self.client.get(self.endpoint("/v1/models")))
+            .timeout(Duration::from_secs(10))
+            .send()
+            .await?;
+        
+        if !response.status().is_success() {
+            anyhow::bail!("Failed to get models: {}", response.status());
+        }
+        
+        let response_json: serde_json::Value = serde_json::from_str(&read_text(response, &self.invalid_utf8).await?)?;
+        Ok(response_json["data"].as_array().into_iter().flatten()
+            .filter_map(|model| {
+                let name = model["id"].as_str()?;
+                Some(ModelInfo {
+                    name: name.to_string(),
+                    size: UNKNOWN_FIELD.to_string(),
+                    family: model["owned_by"].as_str().unwrap_or(UNKNOWN_FIELD).to_string(),
+                    format: UNKNOWN_FIELD.to_string(),
+                })
+            })
+            .collect())
+    }
+    
+    /// Pulls `model_name` unless the server already has it, forwarding the
+    /// pull's progress to `progress` after a status naming the missing model
+    pub async fn ensure_model(&self, model_name: &str, progress: &Sender<PullProgress>) -> anyhow::Result<()> {
+        // На свежей установке моделей нет вовсе - это не ошибка, а повод скачать
+        let models = match self.list_models().await {
+            Ok(models) => models,
+            Err(e) if e.is::<NoModelsInstalled>() => Vec::new(),
+            Err(e) => return Err(e),
+        };
         
         if !models.iter().any(|m| m.contains(model_name)) {
-            println!("Model {} not found, pulling...", model_name);
-            self.pull_model(model_name).await?;
+            let _ = progress.send(PullProgress {
+                status: format!("model {} not found, pulling", model_name),
+                completed: None,
+                total: None,
+            });
+            self.pull_model(model_name, progress).await?;
         }
         
         Ok(())
     }
     
-    async fn pull_model(&self, model_name: &str) -> anyhow::Result<()> {
+    /// Pulls `model_name` with `/api/pull`, sending a `PullProgress` for
+    /// every status line the server streams. Fails when the server reports
+    /// an error, stays silent for 5 minutes or ends before "success".
+    /// A dropped receiver does not stop the pull.
+    pub async fn pull_model(&self, model_name: &str, progress: &Sender<PullProgress>) -> anyhow::Result<()> {
+        if self.backend != Backend::Ollama {
+            anyhow::bail!("{} servers cannot pull models; load {} on the server", self.backend.label(), model_name);
+        }
         let payload = json!({
-            "name": model_name
+            "name": model_name,
+            "stream": true
         });
         
-        let response = self.client
-            .post(format!("{}/api/pull", self.url))
+        let mut response = self.authorized(self.client.post(self.endpoint("/api/pull")))
             .json(&payload)
-            .timeout(Duration::from_secs(300))
+            .timeout(MAX_PULL_DURATION)
             .send()
             .await?;
         
         if !response.status().is_success() {
             let status = response.status();
-            let error_text = response.text().await.unwrap_or_default();
+            let error_text = self.error_body(response).await;
             anyhow::bail!("Failed to pull model {}: {}", status, error_text);
         }
         
+        let mut lines = NdjsonLines::default();
+        let mut succeeded = false;
+        loop {
+            let chunk = tokio::time::timeout(PULL_STALL_TIMEOUT, response.chunk()).await
+                .map_err(|_| anyhow::anyhow!("Pull of {} stalled: no data for {}s", model_name, PULL_STALL_TIMEOUT.as_secs()))??;
+            let (batch, finished) = match chunk {
+                Some(bytes) => (lines.push(&bytes), false),
+                None => (lines.finish().into_iter().collect(), true),
+            };
+            for line in batch {
+                let update = PullProgress::parse(&line)?;
+                succeeded = update.is_success();
+                let _ = progress.send(update);
+            }
+            if finished {
+                break;
+            }
+        }
+        
+        if !succeeded {
+            anyhow::bail!("Pull of {} ended before the server reported success", model_name);
+        }
         Ok(())
     }
 }
 
+/// Recursively merges `overlay` into `base`: objects are merged key by key,
+/// any other value in the overlay replaces the one in `base`
+pub fn merge_json(base: &mut serde_json::Value, overlay: &serde_json::Value) {
+    match (base, overlay) {
+        (serde_json::Value::Object(base_map), serde_json::Value::Object(overlay_map)) => {
+            for (key, value) in overlay_map {
+                merge_json(base_map.entry(key.clone()).or_insert(serde_json::Value::Null), value);
+            }
+        }
+        (base, overlay) => *base = overlay.clone(),
+    }
+}
+
+/// Decodes a response body as UTF-8 and counts the invalid sequences. A
+/// misbehaving model or proxy can send them; they become U+FFFD instead of
+/// failing the request, and the caller decides whether to report the count.
+pub fn decode_lossy(bytes: &[u8]) -> (String, usize) {
+    let invalid = bytes.utf8_chunks().filter(|chunk| !chunk.invalid().is_empty()).count();
+    (String::from_utf8_lossy(bytes).into_owned(), invalid)
+}
+
+/// Body of `response` decoded with `decode_lossy`; the replaced sequences
+/// are added to `invalid_utf8`
+pub async fn read_text(response: reqwest::Response, invalid_utf8: &AtomicUsize) -> anyhow::Result<String> {
+    let bytes = response.bytes().await?;
+    let (text, invalid) = decode_lossy(&bytes);
+    invalid_utf8.fetch_add(invalid, Ordering::Relaxed);
+    Ok(text)
+}
+
+/// Parses an `/api/chat` body defensively. Some Ollama versions return
+/// several concatenated JSON objects for a non-streaming request, which
+/// makes a plain `serde_json::from_str` fail with "trailing characters".
+/// Returns the last object carrying a `message` field, or else the last
+/// complete object in the body.
+pub fn parse_chat_response(body: &str) -> anyhow::Result<serde_json::Value> {
+    let mut last_object = None;
+    let mut last_message = None;
+    
+    for value in serde_json::Deserializer::from_str(body).into_iter::<serde_json::Value>() {
+        match value {
+            Ok(value) => {
+                if value.get("message").is_some() {
+                    last_message = Some(value.clone());
+                }
+                last_object = Some(value);
+            }
+            // Обрезанный хвост: используем то, что уже разобрали
+            Err(e) if last_object.is_some() && e.is_eof() => break,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    
+    last_message
+        .or(last_object)
+        .ok_or_else(|| anyhow::anyhow!("Empty response body"))
+}
+
+/// Splits a newline-delimited JSON body that arrives in arbitrary
+/// network chunks into lines. A line cut between two chunks (even inside
+/// a multi-byte character) is held back until its end arrives.
+#[derive(Debug, Default)]
+pub struct NdjsonLines {
+    partial: Vec<u8>,
+}
+
+impl NdjsonLines {
+    /// Complete, non-blank lines finished by `chunk`
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.partial.extend_from_slice(chunk);
+        let Some(end) = self.partial.iter().rposition(|&byte| byte == b'\n') else {
+            return Vec::new();
+        };
+        let rest = self.partial.split_off(end + 1);
+        let complete = std::mem::replace(&mut self.partial, rest);
+        complete.split(|&byte| byte == b'\n')
+            .map(|line| String::from_utf8_lossy(line).trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect()
+    }
+    
+    /// The last line when the body did not end with a newline
+    pub fn finish(&mut self) -> Option<String> {
+        let line = String::from_utf8_lossy(&std::mem::take(&mut self.partial)).trim().to_string();
+        (!line.is_empty()).then_some(line)
+    }
+}
+
+/// One line of a streamed `/api/chat` response: the `message.content`
+/// delta and whether it is the `done` chunk. An `error` line fails.
+pub fn parse_stream_chunk(line: &str) -> anyhow::Result<(String, bool)> {
+    let chunk: serde_json::Value = serde_json::from_str(line)?;
+    if let Some(error) = chunk["error"].as_str() {
+        anyhow::bail!("Ollama stream error: {}", error);
+    }
+    let content = chunk["message"]["content"].as_str().unwrap_or_default().to_string();
+    Ok((content, chunk["done"].as_bool().unwrap_or(false)))
+}
+
+/// One line of a streamed OpenAI-compatible response (server-sent events):
+/// the `choices[0].delta.content` of a `data:` line, done at `data: [DONE]`.
+/// Other lines, e.g. comments and event names, carry nothing.
+pub fn parse_sse_chunk(line: &str) -> anyhow::Result<(String, bool)> {
+    let Some(data) = line.strip_prefix("data:").map(str::trim) else {
+        return Ok((String::new(), false));
+    };
+    if data == "[DONE]" {
+        return Ok((String::new(), true));
+    }
+    let chunk: serde_json::Value = serde_json::from_str(data)?;
+    if let Some(error) = chunk.get("error") {
+        let message = error["message"].as_str().map(String::from).unwrap_or_else(|| error.to_string());
+        anyhow::bail!("OpenAI-compatible stream error: {}", message);
+    }
+    let content = chunk["choices"][0]["delta"]["content"].as_str().unwrap_or_default().to_string();
+    Ok((content, false))
+}
+
+enum StreamState {
+    Start(serde_json::Value),
+    Reading(ChatStream),
+    Done,
+}
+
+/// Response of a streamed chat request, read line by line
+struct ChatStream {
+    response: reqwest::Response,
+    backend: Backend,
+    lines: NdjsonLines,
+    pending: VecDeque<String>,
+    /// Longest wait for the next network chunk
+    idle_timeout: Duration,
+}
+
+impl ChatStream {
+    fn new(response: reqwest::Response, backend: Backend, idle_timeout: Duration) -> Self {
+        Self { response, backend, lines: NdjsonLines::default(), pending: VecDeque::new(), idle_timeout }
+    }
+    
+    /// Next parsed line; the body ending before a `done` chunk, or going
+    /// quiet for `idle_timeout`, is an error
+    async fn next_chunk(&mut self) -> anyhow::Result<(String, bool)> {
+        loop {
+            if let Some(line) = self.pending.pop_front() {
+                return self.backend.parse_stream_line(&line);
+            }
+            let chunk = tokio::time::timeout(self.idle_timeout, self.response.chunk()).await
+                .map_err(|_| anyhow::anyhow!("{} stream stalled: no data for {}s", self.backend.label(), self.idle_timeout.as_secs()))??;
+            match chunk {
+                Some(bytes) => self.pending.extend(self.lines.push(&bytes)),
+                None => match self.lines.finish() {
+                    Some(line) => self.pending.push_back(line),
+                    None => anyhow::bail!("Ollama stream ended without a done chunk"),
+                },
+            }
+        }
+    }
+}
+
 /// Информация о модели
 #[derive(Debug, Clone)]
 pub struct ModelInfo {
@@ -227,8 +1359,297 @@ pub struct ModelInfo {
     pub format: String,
 }
 
+/// Placeholder for a `ModelInfo` field the server did not report
+const UNKNOWN_FIELD: &str = "unknown";
+
+impl ModelInfo {
+    /// One entry of the `/api/tags` `models` array; `None` without a name
+    pub fn from_tags_entry(entry: &serde_json::Value) -> Option<Self> {
+        let name = entry["name"].as_str()?;
+        let details = &entry["details"];
+        let field = |value: &serde_json::Value| value.as_str().filter(|s| !s.is_empty()).unwrap_or(UNKNOWN_FIELD).to_string();
+        Some(Self {
+            name: name.to_string(),
+            size: entry["size"].as_u64().map_or_else(|| UNKNOWN_FIELD.to_string(), format_size),
+            family: field(&details["family"]),
+            format: field(&details["format"]),
+        })
+    }
+}
+
+/// One status line of a streamed `/api/pull`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PullProgress {
+    /// "pulling manifest", "pulling <digest>", "verifying sha256 digest", "success", ...
+    pub status: String,
+    /// Bytes of the current layer downloaded so far
+    pub completed: Option<u64>,
+    /// Size of the current layer
+    pub total: Option<u64>,
+}
+
+impl PullProgress {
+    /// Parses one line; an `error` line fails
+    pub fn parse(line: &str) -> anyhow::Result<Self> {
+        let value: serde_json::Value = serde_json::from_str(line)?;
+        if let Some(error) = value["error"].as_str() {
+            anyhow::bail!("Ollama pull error: {}", error);
+        }
+        Ok(Self {
+            status: value["status"].as_str().unwrap_or_default().to_string(),
+            completed: value["completed"].as_u64(),
+            total: value["total"].as_u64(),
+        })
+    }
+    
+    /// Share of the current layer downloaded, 0-100, when the server
+    /// reported byte counts
+    pub fn percent(&self) -> Option<f64> {
+        let total = self.total.filter(|&total| total > 0)?;
+        Some(self.completed.unwrap_or(0).min(total) as f64 * 100.0 / total as f64)
+    }
+    
+    /// The final line of a successful pull
+    pub fn is_success(&self) -> bool {
+        self.status == "success"
+    }
+}
+
+impl std::fmt::Display for PullProgress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.percent() {
+            Some(percent) => write!(f, "{} {:.0}%", self.status, percent),
+            None => write!(f, "{}", self.status),
+        }
+    }
+}
+
+/// Result of `Ollama::check_health`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ConnectionStatus {
+    /// The server accepted the connection and answered
+    pub reachable: bool,
+    /// The model list came back as a valid list
+    pub model_api_ok: bool,
+    /// Installed models; 0 unless `model_api_ok`
+    pub models_count: usize,
+}
+
+impl ConnectionStatus {
+    /// No answer at all: connection refused, reset or timed out
+    pub const UNREACHABLE: Self = Self { reachable: false, model_api_ok: false, models_count: 0 };
+    
+    /// The server answered; `models_count` is `None` when the model list
+    /// request failed or its body was not a model list
+    pub fn answered(models_count: Option<usize>) -> Self {
+        Self { reachable: true, model_api_ok: models_count.is_some(), models_count: models_count.unwrap_or(0) }
+    }
+}
+
+/// Models in an `/api/tags` body. An empty list is `Ok`; a body without a
+/// `models` array is an error, since it did not come from the model API
+pub fn parse_model_list(body: &serde_json::Value) -> anyhow::Result<Vec<ModelInfo>> {
+    let models = body.get("models")
+        .ok_or_else(|| anyhow::anyhow!("Model list has no `models` field; is this an Ollama server?"))?
+        .as_array()
+        .ok_or_else(|| anyhow::anyhow!("Model list field `models` is not an array"))?;
+    Ok(models.iter().filter_map(ModelInfo::from_tags_entry).collect())
+}
+
+/// Byte count in decimal units the way `ollama list` prints it: "4.1 GB"
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1000 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1000.0;
+    let mut unit = 0;
+    // 999.96 KB округляется до "1000.0 KB" - переходим к следующей единице заранее
+    while value >= 999.95 && unit + 1 < UNITS.len() {
+        value /= 1000.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
 impl std::fmt::Display for ModelInfo {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{} ({}, {})", self.name, self.size, self.family)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn parse_chat_response_takes_the_message_of_concatenated_objects() {
+        let body = concat!(
+            r#"{"model":"llama3.2","message":{"role":"assistant","content":"first"},"done":false}"#,
+            r#"{"model":"llama3.2","message":{"role":"assistant","content":"second"},"done":true}"#,
+            r#"{"model":"llama3.2","done":true,"total_duration":42}"#,
+        );
+        
+        let response = parse_chat_response(body).unwrap();
+        assert_eq!(response["message"]["content"], "second");
+    }
+    
+    #[test]
+    fn chat_payload_puts_the_system_prompt_first_in_messages() {
+        let ollama = Ollama::new(DEFAULT_OLLAMA_URL, "llama3.2")
+            .with_primer(vec![
+                ChatTurn { role: "user".to_string(), content: "example".to_string() },
+                ChatTurn { role: "assistant".to_string(), content: "reply".to_string() },
+            ]);
+        
+        let payload = ollama.chat_payload("prompt", None, None, Some("be careful"), &[]);
+        let messages = payload["messages"].as_array().unwrap();
+        assert_eq!(messages[0], json!({ "role": "system", "content": "be careful" }));
+        assert_eq!(messages.last().unwrap()["content"], "prompt");
+        assert!(payload.get("system").is_none(), "Ollama ignores a top-level system key");
+    }
+    
+    #[test]
+    fn decode_lossy_replaces_invalid_utf8() {
+        let bytes = b"{\"content\":\"ok \xff\xfe \xd0\"}";
+        assert_eq!(decode_lossy(bytes), ("{\"content\":\"ok \u{FFFD}\u{FFFD} \u{FFFD}\"}".to_string(), 3));
+        assert_eq!(decode_lossy("привет".as_bytes()), ("привет".to_string(), 0));
+    }
+    
+    #[tokio::test]
+    async fn read_text_accepts_a_body_with_invalid_utf8() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = [0u8; 1024];
+            let _ = stream.read(&mut request).await;
+            let body: &[u8] = b"caf\xc3\xa9 \xc3";
+            let head = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", body.len());
+            stream.write_all(head.as_bytes()).await.unwrap();
+            stream.write_all(body).await.unwrap();
+        });
+        
+        let invalid_utf8 = AtomicUsize::new(0);
+        let response = reqwest::get(format!("http://{}/", addr)).await.unwrap();
+        assert_eq!(read_text(response, &invalid_utf8).await.unwrap(), "café \u{FFFD}");
+        assert_eq!(invalid_utf8.load(Ordering::Relaxed), 1);
+    }
+    
+    #[tokio::test]
+    async fn with_retries_succeeds_after_two_transient_failures() {
+        use crate::testing::{save_fixture, HttpExchange, HttpMode, HttpRecorder};
+        
+        let fixtures = std::env::temp_dir().join(format!("apet_retry_fixtures_{}", std::process::id()));
+        let probe = Ollama::new(DEFAULT_OLLAMA_URL, "llama3.2");
+        let request_body = serde_json::to_string(&probe.wire_payload(probe.chat_payload("hi", None, None, None, &[]))).unwrap();
+        let answers = [
+            (500, r#"{"error":"model runner crashed"}"#),
+            (503, r#"{"error":"server busy"}"#),
+            (200, r#"{"message":{"role":"assistant","content":"ok"},"done":true}"#),
+        ];
+        for (index, (status, body)) in answers.into_iter().enumerate() {
+            let exchange = HttpExchange {
+                method: "POST".to_string(),
+                path: "/api/chat".to_string(),
+                request_body: request_body.clone(),
+                status,
+                response_body: body.to_string(),
+            };
+            save_fixture(&fixtures, &exchange, index).unwrap();
+        }
+        
+        let recorder = HttpRecorder::start(HttpMode::Replay, &fixtures).await.unwrap();
+        let ollama = Ollama::new(&recorder.url(), "llama3.2").with_retries(2);
+        let result = ollama.generate("hi").await;
+        std::fs::remove_dir_all(&fixtures).ok();
+        
+        assert_eq!(result.unwrap(), "ok");
+        assert_eq!(ollama.transient_retries(), 2);
+    }
+    
+    #[test]
+    fn ndjson_lines_hold_a_line_split_inside_a_character_until_its_newline() {
+        let first = r#"{"message":{"content":"привет"},"done":false}"#.as_bytes();
+        let last = r#"{"message":{"content":"!"},"done":true}"#.as_bytes();
+        // "р" занимает два байта; режем между ними
+        let cut = first.iter().position(|&byte| byte == 0xd1).unwrap() + 1;
+        let mut lines = NdjsonLines::default();
+        
+        assert!(lines.push(&first[..cut]).is_empty());
+        assert!(lines.push(&first[cut..]).is_empty());
+        let line = lines.push(b"\n");
+        assert_eq!(line, vec![String::from_utf8(first.to_vec()).unwrap()]);
+        assert_eq!(Backend::Ollama.parse_stream_line(&line[0]).unwrap(), ("привет".to_string(), false));
+        
+        assert!(lines.push(&last[..5]).is_empty());
+        let line = lines.push(&[&last[5..], b"\n"].concat());
+        assert_eq!(Backend::Ollama.parse_stream_line(&line[0]).unwrap(), ("!".to_string(), true));
+        assert_eq!(lines.finish(), None);
+    }
+    
+    #[tokio::test]
+    async fn generate_stream_ends_at_the_done_line() {
+        use futures_util::StreamExt;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = [0u8; 4096];
+            let _ = stream.read(&mut request).await;
+            stream.write_all(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n").await.unwrap();
+            let body = concat!(
+                r#"{"message":{"content":"café"},"done":false}"#, "\n",
+                r#"{"message":{"content":"!"},"done":true}"#, "\n",
+                "not json after done\n",
+            ).as_bytes();
+            // Первый кусок обрывается внутри "é"
+            let cut = body.iter().position(|&byte| byte == 0xc3).unwrap() + 1;
+            for chunk in [&body[..cut], &body[cut..]] {
+                stream.write_all(format!("{:x}\r\n", chunk.len()).as_bytes()).await.unwrap();
+                stream.write_all(chunk).await.unwrap();
+                stream.write_all(b"\r\n").await.unwrap();
+                stream.flush().await.unwrap();
+            }
+            stream.write_all(b"0\r\n\r\n").await.unwrap();
+        });
+        
+        let ollama = Ollama::new(&format!("http://{}", addr), "llama3.2");
+        let chunks: Vec<String> = ollama.generate_stream("hi").map(Result::unwrap).collect().await;
+        assert_eq!(chunks, vec!["café".to_string(), "!".to_string()]);
+    }
+    
+    #[tokio::test]
+    async fn generate_stream_outlives_the_request_timeout_while_chunks_arrive() {
+        use futures_util::StreamExt;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = [0u8; 4096];
+            let _ = stream.read(&mut request).await;
+            stream.write_all(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n").await.unwrap();
+            let lines = [
+                r#"{"message":{"content":"slow "},"done":false}"#,
+                r#"{"message":{"content":"reply"},"done":true}"#,
+            ];
+            for line in lines {
+                let line = format!("{}\n", line);
+                stream.write_all(format!("{:x}\r\n{}\r\n", line.len(), line).as_bytes()).await.unwrap();
+                stream.flush().await.unwrap();
+                tokio::time::sleep(Duration::from_millis(700)).await;
+            }
+            stream.write_all(b"0\r\n\r\n").await.unwrap();
+        });
+        
+        let ollama = Ollama::new(&format!("http://{}", addr), "llama3.2").with_timeout(Duration::from_millis(500));
+        let chunks: Vec<String> = ollama.generate_stream("hi").map(Result::unwrap).collect().await;
+        assert_eq!(chunks.concat(), "slow reply");
+    }
+}
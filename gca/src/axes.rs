@@ -0,0 +1,202 @@
+use serde::{Deserialize, Serialize};
+
+/// Technique classes of the front-end classifier, first grid axis
+pub const TECHNIQUE_BINS: [&str; 5] = [
+    "roleplay",
+    "system prompt extraction",
+    "direct bypass",
+    "authority impersonation",
+    "general manipulation",
+];
+/// Prompt length classes of the front-end classifier, second grid axis
+pub const COMPLEXITY_BINS: [&str; 4] = ["simple", "medium", "complex", "very complex"];
+/// Trigger keywords of each technique bin but the last, matched as
+/// case-insensitive substrings in this order; the first bin with a match
+/// wins and the last bin is the fallback
+pub const TECHNIQUE_KEYWORDS: [&[&str]; 4] = [
+    &["roleplay", "представь", "pretend"],
+    &["system", "prompt", "инструкции"],
+    &["ignore", "забудь", "bypass"],
+    &["admin", "root", "администратор"],
+];
+/// A prompt longer (in bytes) than the n-th bound is in complexity bin n + 1
+pub const COMPLEXITY_THRESHOLDS: [usize; 3] = [50, 100, 200];
+
+const _: () = assert!(TECHNIQUE_KEYWORDS.len() + 1 == TECHNIQUE_BINS.len());
+const _: () = assert!(COMPLEXITY_THRESHOLDS.len() + 1 == COMPLEXITY_BINS.len());
+/// Word (or token) count classes of `AdversarialPrompt::get_length_dimension`
+pub const LENGTH_BINS: [&str; 3] = ["short", "medium", "long"];
+/// Classes of `adversarial::aggressiveness_level`
+pub const AGGRESSIVENESS_BINS: [&str; 3] = ["soft", "moderate", "aggressive"];
+
+/// Technique bin of `prompt` per `TECHNIQUE_KEYWORDS`
+pub fn classify_technique(prompt: &str) -> usize {
+    let lower = prompt.to_lowercase();
+    TECHNIQUE_KEYWORDS.iter()
+        .position(|keywords| keywords.iter().any(|keyword| lower.contains(keyword)))
+        .unwrap_or(TECHNIQUE_KEYWORDS.len())
+}
+
+/// One technique class of a `TechniqueMapping`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TechniqueRule {
+    pub name: String,
+    /// Case-insensitive substrings that put a prompt in this class
+    pub keywords: Vec<String>,
+    /// Settles prompts that match several classes: the lowest priority
+    /// wins, and equal priorities go to the class listed first
+    #[serde(default)]
+    pub priority: u32,
+}
+
+/// Keyword to technique table of the front-end classifier, loadable from
+/// JSON (`gca cli --techniques techniques.json`). Each rule is one grid row
+/// in list order; `fallback` is the last row, for prompts matching no rule.
+/// The default is `TECHNIQUE_BINS` with `TECHNIQUE_KEYWORDS`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TechniqueMapping {
+    pub techniques: Vec<TechniqueRule>,
+    pub fallback: String,
+}
+
+impl Default for TechniqueMapping {
+    fn default() -> Self {
+        Self {
+            techniques: TECHNIQUE_BINS.iter().zip(TECHNIQUE_KEYWORDS)
+                .enumerate()
+                .map(|(i, (name, keywords))| TechniqueRule {
+                    name: name.to_string(),
+                    keywords: keywords.iter().map(|keyword| keyword.to_string()).collect(),
+                    priority: i as u32,
+                })
+                .collect(),
+            fallback: TECHNIQUE_BINS[TECHNIQUE_KEYWORDS.len()].to_string(),
+        }
+    }
+}
+
+impl TechniqueMapping {
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let mapping: Self = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        mapping.validate()?;
+        Ok(mapping)
+    }
+
+    /// At least one technique, each with a keyword, and no repeated names
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.techniques.is_empty() {
+            anyhow::bail!("techniques: at least one technique is required");
+        }
+        let mut names: Vec<&str> = Vec::new();
+        for (i, rule) in self.techniques.iter().enumerate() {
+            if rule.name.trim().is_empty() {
+                anyhow::bail!("techniques[{}]: name is empty", i);
+            }
+            if rule.keywords.iter().all(|keyword| keyword.trim().is_empty()) {
+                anyhow::bail!("techniques[{}] ({}): no keywords", i, rule.name);
+            }
+            names.push(&rule.name);
+        }
+        names.push(&self.fallback);
+        for (i, name) in names.iter().enumerate() {
+            if names[..i].contains(name) {
+                anyhow::bail!("technique '{}' is listed twice", name);
+            }
+        }
+        Ok(())
+    }
+
+    /// Technique bin of `prompt`: the matching rule with the lowest
+    /// priority, or the fallback bin
+    pub fn classify(&self, prompt: &str) -> usize {
+        let lower = prompt.to_lowercase();
+        self.techniques.iter()
+            .enumerate()
+            .filter(|(_, rule)| {
+                rule.keywords.iter()
+                    .map(|keyword| keyword.trim().to_lowercase())
+                    .any(|keyword| !keyword.is_empty() && lower.contains(&keyword))
+            })
+            .min_by_key(|(i, rule)| (rule.priority, *i))
+            .map_or(self.techniques.len(), |(i, _)| i)
+    }
+
+    /// Technique names in bin order, the fallback last
+    pub fn bins(&self) -> Vec<String> {
+        self.techniques.iter()
+            .map(|rule| rule.name.clone())
+            .chain(std::iter::once(self.fallback.clone()))
+            .collect()
+    }
+
+    /// `BehaviorAxes::technique_complexity` with these techniques as rows
+    pub fn axes(&self) -> BehaviorAxes {
+        BehaviorAxes {
+            x: Axis { name: "technique".to_string(), bins: self.bins() },
+            y: Axis::new("complexity", &COMPLEXITY_BINS),
+        }
+    }
+}
+
+/// Complexity bin of `prompt` per `COMPLEXITY_THRESHOLDS`
+pub fn classify_complexity(prompt: &str) -> usize {
+    let length = prompt.to_lowercase().len();
+    COMPLEXITY_THRESHOLDS.iter().filter(|&&bound| length > bound).count()
+}
+
+/// One behavior dimension: its name and a label per bin
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Axis {
+    pub name: String,
+    pub bins: Vec<String>,
+}
+
+impl Axis {
+    fn new(name: &str, bins: &[&str]) -> Self {
+        Self { name: name.to_string(), bins: bins.iter().map(|bin| bin.to_string()).collect() }
+    }
+
+    /// Label of bin `index`; `<name> <index>` past the known bins
+    pub fn label(&self, index: usize) -> String {
+        self.bins.get(index).cloned().unwrap_or_else(|| format!("{} {}", self.name, index))
+    }
+}
+
+/// What the two grid coordinates encode. Older CLI/GUI results files hold a
+/// technique x complexity grid, not the library archive's, so each export
+/// records its own axes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BehaviorAxes {
+    /// First coordinate (grid rows)
+    pub x: Axis,
+    /// Second coordinate (grid columns)
+    pub y: Axis,
+}
+
+impl BehaviorAxes {
+    /// Grid of results files written before the front-ends showed the
+    /// library archive
+    pub fn technique_complexity() -> Self {
+        Self { x: Axis::new("technique", &TECHNIQUE_BINS), y: Axis::new("complexity", &COMPLEXITY_BINS) }
+    }
+
+    /// Library archive (`AdversarialPrompt::coordinates`)
+    pub fn length_aggressiveness() -> Self {
+        Self { x: Axis::new("length", &LENGTH_BINS), y: Axis::new("aggressiveness", &AGGRESSIVENESS_BINS) }
+    }
+
+    /// Cell description such as `technique: roleplay, complexity: simple`
+    pub fn describe(&self, (x, y): (usize, usize)) -> String {
+        format!("{}: {}, {}: {}", self.x.name, self.x.label(x), self.y.name, self.y.label(y))
+    }
+
+    /// One line per axis listing its bins, e.g. `technique: 0=roleplay, 1=...`
+    pub fn legend(&self) -> Vec<String> {
+        [&self.x, &self.y].iter()
+            .map(|axis| {
+                let bins: Vec<String> = axis.bins.iter().enumerate().map(|(i, bin)| format!("{}={}", i, bin)).collect();
+                format!("{}: {}", axis.name, bins.join(", "))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,315 @@
+#[cfg(feature = "anthropic")]
+use crate::ai::anthropic::AnthropicBackend;
+use crate::ai::backend::LlmBackend;
+#[cfg(feature = "gemini")]
+use crate::ai::gemini::GeminiBackend;
+use crate::ai::{default_ollama_url, Backend, ChatTurn, Ollama, DEFAULT_OPENAI_URL};
+use crate::filter::StaticFilter;
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+
+/// Limits shared with the GUI sliders
+pub const GENERATIONS_RANGE: RangeInclusive<usize> = 1..=10;
+pub const POPULATION_RANGE: RangeInclusive<usize> = 4..=20;
+
+/// User settings loaded from a JSON file (`gca cli --config apet.json`).
+/// Every field is optional; missing ones keep their defaults.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub run: RunConfig,
+    pub ascii_grid: AsciiGridConfig,
+    /// Few-shot turns prepended to every chat request
+    pub primer: Vec<ChatTurn>,
+    /// Simulated content filter in front of the target
+    pub filter: FilterConfig,
+    /// Attack goals used as seed requests; the built-in list when empty
+    pub goals: Vec<String>,
+    /// Decimals kept for scores in exported files (`DEFAULT_SCORE_PRECISION` when unset)
+    pub score_precision: Option<u32>,
+    /// Backends tried in order per request (`FallbackBackend`); the local
+    /// Ollama alone when empty
+    pub backends: Vec<BackendConfig>,
+}
+
+impl Config {
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        let config: Self = serde_json::from_str(&content)?;
+        config.ascii_grid.validate()?;
+        if let Some(turn) = config.primer.iter().find(|t| !matches!(t.role.as_str(), "user" | "assistant" | "system")) {
+            anyhow::bail!("primer: unknown role '{}'", turn.role);
+        }
+        StaticFilter::new(&config.filter).map_err(|e| anyhow::anyhow!("filter: {}", e))?;
+        for (i, backend) in config.backends.iter().enumerate() {
+            backend.validate().map_err(|e| anyhow::anyhow!("backends[{}]: {}", i, e))?;
+        }
+        Ok(config)
+    }
+
+    /// Mistakes that load fine but waste or silently break a run, one
+    /// actionable message each (`gca check-config`)
+    pub fn lint(&self) -> Vec<String> {
+        let mut warnings = self.run.clone().clamp();
+
+        let mut seen: Vec<(String, usize)> = Vec::new();
+        for (i, goal) in self.goals.iter().enumerate() {
+            let normalized = goal.trim().to_lowercase();
+            if normalized.is_empty() {
+                warnings.push(format!("goals[{}] is empty; remove it or write the goal text", i));
+            } else if let Some((_, first)) = seen.iter().find(|(g, _)| *g == normalized) {
+                warnings.push(format!("goals[{}] duplicates goals[{}]; duplicates only repeat the same seed", i, first));
+            } else {
+                seen.push((normalized, i));
+            }
+        }
+
+        let goals = self.goals.len();
+        if goals > 0 && self.run.population < goals {
+            warnings.push(format!(
+                "population {} is smaller than the {} goals: goals[{}..] never get a seed prompt; \
+                 raise the population to at least {} or drop goals",
+                self.run.population, goals, self.run.population, goals
+            ));
+        }
+
+        warnings
+    }
+}
+
+/// Provider kinds accepted in `BackendConfig::kind`
+pub const BACKEND_KINDS: [&str; 4] = ["ollama", "openai", "anthropic", "gemini"];
+
+/// One link of the fallback chain, e.g.
+/// `{"kind": "ollama", "url": "http://gpu-box:11434"}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackendConfig {
+    /// One of `BACKEND_KINDS`
+    pub kind: String,
+    /// Base URL; the provider's default when unset
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Model name; the run's model when unset
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+impl BackendConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        match self.kind.as_str() {
+            "ollama" | "openai" => Ok(()),
+            "anthropic" if cfg!(feature = "anthropic") => Ok(()),
+            "anthropic" => Err("the anthropic backend needs a build with --features anthropic".to_string()),
+            "gemini" if cfg!(feature = "gemini") => Ok(()),
+            "gemini" => Err("the gemini backend needs a build with --features gemini".to_string()),
+            other => Err(format!("unknown kind '{}', expected one of {}", other, BACKEND_KINDS.join(", "))),
+        }
+    }
+
+    /// The backend this entry describes; Anthropic reads its key from
+    /// `ANTHROPIC_API_KEY`, Gemini from `GEMINI_API_KEY`, an
+    /// OpenAI-compatible server from `OPENAI_API_KEY` when that is set
+    pub fn build(&self, default_model: &str) -> anyhow::Result<Box<dyn LlmBackend>> {
+        self.build_with(default_model, |ollama| ollama)
+    }
+
+    /// `build`, passing the client of an `ollama` or `openai` entry through
+    /// `configure`, e.g. to set the primer or retries of a run
+    pub fn build_with(&self, default_model: &str, configure: impl Fn(Ollama) -> Ollama) -> anyhow::Result<Box<dyn LlmBackend>> {
+        self.validate().map_err(|e| anyhow::anyhow!(e))?;
+        let model = self.model.as_deref().unwrap_or(default_model);
+        match self.kind.as_str() {
+            #[cfg(feature = "anthropic")]
+            "anthropic" => {
+                let backend = AnthropicBackend::from_env(model)?;
+                Ok(Box::new(match &self.url {
+                    Some(url) => backend.with_url(url),
+                    None => backend,
+                }))
+            }
+            #[cfg(feature = "gemini")]
+            "gemini" => {
+                let backend = GeminiBackend::from_env(model)?;
+                Ok(Box::new(match &self.url {
+                    Some(url) => backend.with_url(url),
+                    None => backend,
+                }))
+            }
+            "openai" => {
+                let client = Ollama::new(self.url.as_deref().unwrap_or(DEFAULT_OPENAI_URL), model)
+                    .with_backend(Backend::OpenAiCompatible);
+                Ok(Box::new(configure(match std::env::var("OPENAI_API_KEY") {
+                    Ok(key) if !key.trim().is_empty() => client.with_api_key(key.trim()),
+                    _ => client,
+                })))
+            }
+            _ => Ok(Box::new(configure(Ollama::new(&self.url.clone().unwrap_or_else(default_ollama_url), model)))),
+        }
+    }
+}
+
+/// Size of a run and the model it targets. Resolved in layers, each
+/// overriding the previous: defaults, config file, `APET_*` environment
+/// variables, command-line flags.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RunConfig {
+    pub generations: usize,
+    /// Requests per generation
+    pub population: usize,
+    pub model: String,
+}
+
+impl Default for RunConfig {
+    fn default() -> Self {
+        Self {
+            generations: 3,
+            population: 8,
+            model: "llama3.2".to_string(),
+        }
+    }
+}
+
+impl RunConfig {
+    /// Applies `APET_GENERATIONS`, `APET_POPULATION` and `APET_MODEL` when
+    /// they are set
+    pub fn apply_env(&mut self) -> Result<(), String> {
+        self.apply_overrides(|name| std::env::var(name).ok())
+    }
+
+    /// Same as `apply_env` with a custom variable lookup
+    pub fn apply_overrides(&mut self, lookup: impl Fn(&str) -> Option<String>) -> Result<(), String> {
+        let number = |name: &str, value: String| {
+            value.trim().parse::<usize>().map_err(|e| format!("{}={}: {}", name, value, e))
+        };
+
+        if let Some(value) = lookup("APET_GENERATIONS") {
+            self.generations = number("APET_GENERATIONS", value)?;
+        }
+        if let Some(value) = lookup("APET_POPULATION") {
+            self.population = number("APET_POPULATION", value)?;
+        }
+        if let Some(value) = lookup("APET_MODEL") {
+            if value.trim().is_empty() {
+                return Err("APET_MODEL is empty".to_string());
+            }
+            self.model = value.trim().to_string();
+        }
+        Ok(())
+    }
+
+    /// Pulls every size into its slider range; returns one warning per
+    /// value that had to change
+    pub fn clamp(&mut self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        let mut clamp = |name: &str, value: &mut usize, range: RangeInclusive<usize>| {
+            let clamped = (*value).clamp(*range.start(), *range.end());
+            if clamped != *value {
+                warnings.push(format!("{} {} is outside {}..={}, using {}", name, value, range.start(), range.end(), clamped));
+                *value = clamped;
+            }
+        };
+
+        clamp("generations", &mut self.generations, GENERATIONS_RANGE);
+        clamp("population", &mut self.population, POPULATION_RANGE);
+        warnings
+    }
+}
+
+/// Denylist rules a prompt must evade. A prompt matching any of them is
+/// blocked: it gets `blocked_fitness` and is never sent to the model.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FilterConfig {
+    /// Case-insensitive substrings
+    pub keywords: Vec<String>,
+    /// Regular expressions (`regex` crate syntax); add `(?i)` to ignore case
+    pub patterns: Vec<String>,
+    /// Fitness of a blocked prompt, 0..=1
+    pub blocked_fitness: f64,
+}
+
+impl FilterConfig {
+    pub fn is_empty(&self) -> bool {
+        self.keywords.is_empty() && self.patterns.is_empty()
+    }
+}
+
+/// One heatmap level: fitness strictly above `threshold` is drawn as `symbol`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AsciiLevel {
+    pub threshold: f64,
+    pub symbol: String,
+}
+
+/// Fitness→symbol mapping of the terminal grid heatmap
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AsciiGridConfig {
+    /// Checked from the highest threshold down
+    pub levels: Vec<AsciiLevel>,
+    /// Symbol for occupied cells below every threshold
+    pub below: String,
+    /// Symbol for empty cells
+    pub empty: String,
+}
+
+impl Default for AsciiGridConfig {
+    fn default() -> Self {
+        let level = |threshold, symbol: &str| AsciiLevel { threshold, symbol: symbol.to_string() };
+        Self {
+            levels: vec![
+                level(0.8, "████"),
+                level(0.6, "███░"),
+                level(0.4, "██░░"),
+                level(0.2, "█░░░"),
+            ],
+            below: "▓▓▓▓".to_string(),
+            empty: "░░░░".to_string(),
+        }
+    }
+}
+
+impl AsciiGridConfig {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if let Some(level) = self.levels.iter().find(|l| !l.threshold.is_finite()) {
+            anyhow::bail!("ascii_grid: threshold for '{}' is not a number", level.symbol);
+        }
+        if self.symbols().any(str::is_empty) {
+            anyhow::bail!("ascii_grid: symbols must not be empty");
+        }
+        Ok(())
+    }
+
+    pub fn symbol_for(&self, fitness: f64) -> &str {
+        let mut levels: Vec<&AsciiLevel> = self.levels.iter().collect();
+        levels.sort_by(|a, b| b.threshold.total_cmp(&a.threshold));
+        levels.into_iter()
+            .find(|level| fitness > level.threshold)
+            .map_or(self.below.as_str(), |level| level.symbol.as_str())
+    }
+
+    /// Column width that fits the widest symbol plus a gap
+    pub fn cell_width(&self) -> usize {
+        self.symbols().map(|s| s.chars().count()).max().unwrap_or(1) + 4
+    }
+
+    pub fn legend(&self) -> String {
+        let mut levels: Vec<&AsciiLevel> = self.levels.iter().collect();
+        levels.sort_by(|a, b| b.threshold.total_cmp(&a.threshold));
+
+        let mut parts: Vec<String> = levels.iter()
+            .map(|level| format!("{} (>{})", level.symbol, level.threshold))
+            .collect();
+        let lowest = levels.last().map_or(0.0, |level| level.threshold);
+        parts.push(format!("{} (<={})", self.below, lowest));
+        parts.push(format!("{} (empty)", self.empty));
+        parts.join(" ")
+    }
+
+    fn symbols(&self) -> impl Iterator<Item = &str> {
+        self.levels.iter()
+            .map(|level| level.symbol.as_str())
+            .chain([self.below.as_str(), self.empty.as_str()])
+    }
+}
@@ -0,0 +1,180 @@
+use crate::export::{ArchiveExport, ResultsExport};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Scores are compared with this tolerance so float noise is not a change
+const SCORE_EPSILON: f64 = 1e-9;
+
+/// Cell scores of one saved run, whatever export format it came from
+#[derive(Debug, Clone)]
+pub struct GridSnapshot {
+    pub dimensions: (usize, usize),
+    pub cells: BTreeMap<(usize, usize), f64>,
+}
+
+impl GridSnapshot {
+    /// Loads a front-end `ResultsExport` or a library `ArchiveExport`
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        if let Ok(results) = ResultsExport::load(path) {
+            return Ok(Self::from_results(&results));
+        }
+        let archive = ArchiveExport::load(path)
+            .map_err(|e| anyhow::anyhow!("{}: not a results or archive export ({})", path, e))?;
+        Ok(Self {
+            dimensions: archive.grid_size,
+            cells: archive.elites.iter().map(|elite| (elite.coordinates, elite.success_score)).collect(),
+        })
+    }
+
+    pub fn from_results(results: &ResultsExport) -> Self {
+        Self {
+            dimensions: results.grid_dimensions,
+            cells: results.map_elites_grid.iter().map(|ind| (ind.behavior, ind.fitness)).collect(),
+        }
+    }
+
+    pub fn coverage(&self) -> f64 {
+        let total = self.dimensions.0 * self.dimensions.1;
+        if total == 0 { 0.0 } else { self.cells.len() as f64 / total as f64 }
+    }
+
+    pub fn qd_score(&self) -> f64 {
+        self.cells.values().sum()
+    }
+
+    /// Highest cell score; 0 for an empty grid
+    pub fn best(&self) -> f64 {
+        self.cells.values().copied().fold(0.0, f64::max)
+    }
+}
+
+/// Minimum final stats a run must reach, for pass/fail gating in CI.
+/// Unset bounds are not checked.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RunAssertions {
+    pub coverage: Option<f64>,
+    pub qd_score: Option<f64>,
+    pub best: Option<f64>,
+}
+
+impl RunAssertions {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// One message per bound `snapshot` falls short of; empty when all pass
+    pub fn check(&self, snapshot: &GridSnapshot) -> Vec<String> {
+        let checks = [
+            ("coverage", self.coverage, snapshot.coverage()),
+            ("QD-score", self.qd_score, snapshot.qd_score()),
+            ("best fitness", self.best, snapshot.best()),
+        ];
+        checks.into_iter()
+            .filter_map(|(name, bound, actual)| {
+                let bound = bound?;
+                (actual + SCORE_EPSILON < bound).then(|| format!("{} {:.3} is below the required {:.3}", name, actual, bound))
+            })
+            .collect()
+    }
+}
+
+/// Score of one cell before and after
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CellChange {
+    pub cell: (usize, usize),
+    pub old: Option<f64>,
+    pub new: Option<f64>,
+}
+
+impl CellChange {
+    pub fn delta(&self) -> f64 {
+        self.new.unwrap_or(0.0) - self.old.unwrap_or(0.0)
+    }
+}
+
+/// What changed between two runs over the same grid
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveDiff {
+    pub newly_filled: Vec<CellChange>,
+    pub improved: Vec<CellChange>,
+    pub regressed: Vec<CellChange>,
+    /// Filled before, empty now
+    pub emptied: Vec<CellChange>,
+    pub unchanged: usize,
+    pub coverage_old: f64,
+    pub coverage_new: f64,
+    pub qd_score_old: f64,
+    pub qd_score_new: f64,
+}
+
+impl ArchiveDiff {
+    pub fn between(old: &GridSnapshot, new: &GridSnapshot) -> Self {
+        let mut diff = Self {
+            newly_filled: Vec::new(),
+            improved: Vec::new(),
+            regressed: Vec::new(),
+            emptied: Vec::new(),
+            unchanged: 0,
+            coverage_old: old.coverage(),
+            coverage_new: new.coverage(),
+            qd_score_old: old.qd_score(),
+            qd_score_new: new.qd_score(),
+        };
+
+        let cells: std::collections::BTreeSet<_> = old.cells.keys().chain(new.cells.keys()).copied().collect();
+        for cell in cells {
+            let change = CellChange { cell, old: old.cells.get(&cell).copied(), new: new.cells.get(&cell).copied() };
+            match (change.old, change.new) {
+                (None, Some(_)) => diff.newly_filled.push(change),
+                (Some(_), None) => diff.emptied.push(change),
+                (Some(_), Some(_)) if change.delta() > SCORE_EPSILON => diff.improved.push(change),
+                (Some(_), Some(_)) if change.delta() < -SCORE_EPSILON => diff.regressed.push(change),
+                _ => diff.unchanged += 1,
+            }
+        }
+
+        diff
+    }
+
+    /// No cell got worse or disappeared
+    pub fn is_regression_free(&self) -> bool {
+        self.regressed.is_empty() && self.emptied.is_empty()
+    }
+
+    /// Plain-text report, one line per changed cell
+    pub fn to_table(&self) -> String {
+        let format_score = |score: Option<f64>| score.map_or("-".to_string(), |s| format!("{:.3}", s));
+        let mut out = String::new();
+
+        out.push_str(&format!("{:<10} {:<8} {:>7} {:>7} {:>8}\n", "change", "cell", "old", "new", "delta"));
+        let groups = [
+            ("filled", &self.newly_filled),
+            ("improved", &self.improved),
+            ("regressed", &self.regressed),
+            ("emptied", &self.emptied),
+        ];
+        for (label, changes) in groups {
+            for change in changes {
+                out.push_str(&format!(
+                    "{:<10} {:<8} {:>7} {:>7} {:>+8.3}\n",
+                    label, format!("{},{}", change.cell.0, change.cell.1),
+                    format_score(change.old), format_score(change.new), change.delta()
+                ));
+            }
+        }
+
+        out.push_str(&format!(
+            "\nfilled {}, improved {}, regressed {}, emptied {}, unchanged {}\n",
+            self.newly_filled.len(), self.improved.len(), self.regressed.len(), self.emptied.len(), self.unchanged
+        ));
+        out.push_str(&format!(
+            "coverage {:.1}% -> {:.1}% ({:+.1} pp)\n",
+            self.coverage_old * 100.0, self.coverage_new * 100.0, (self.coverage_new - self.coverage_old) * 100.0
+        ));
+        out.push_str(&format!(
+            "QD-score {:.3} -> {:.3} ({:+.3})\n",
+            self.qd_score_old, self.qd_score_new, self.qd_score_new - self.qd_score_old
+        ));
+        out
+    }
+}
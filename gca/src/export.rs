@@ -0,0 +1,763 @@
+use crate::adversarial::{aggressiveness_level, attack_type_name, AdversarialPrompt};
+use crate::axes::{BehaviorAxes, TechniqueMapping};
+use crate::map_elites::{GenerationStats, MapElitesArchive};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::sync::Mutex;
+
+/// Version of the JSON export format. Bump when a field changes meaning
+/// or is removed; adding optional fields does not require a bump.
+pub const SCHEMA_VERSION: u32 = 1;
+/// Decimals kept for scores by `save`; `save_with_precision` overrides it
+pub const DEFAULT_SCORE_PRECISION: u32 = 4;
+
+/// `value` rounded to `decimals`, or `None` if it is NaN or infinite
+/// (serde_json writes those as `null`, which fails to load back)
+pub fn round_score(value: f64, decimals: u32) -> Option<f64> {
+    if !value.is_finite() {
+        return None;
+    }
+    let factor = 10f64.powi(decimals.min(15) as i32);
+    Some((value * factor).round() / factor)
+}
+
+/// Rounds every score in place; non-finite ones become 0. Returns how many
+/// were replaced, for the caller to report.
+fn sanitize_scores<'a>(scores: impl IntoIterator<Item = &'a mut f64>, decimals: u32) -> usize {
+    let mut replaced = 0;
+    for score in scores {
+        match round_score(*score, decimals) {
+            Some(rounded) => *score = rounded,
+            None => {
+                *score = 0.0;
+                replaced += 1;
+            }
+        }
+    }
+    replaced
+}
+
+/// Provenance of a run, written at the top of every export so a result
+/// can be traced back to the build, model and settings that produced it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunMetadata {
+    /// Version of the APET build (`CARGO_PKG_VERSION`)
+    pub apet_version: String,
+    /// Backend kind: `ollama`, `anthropic` or `fallback`
+    pub backend: String,
+    pub model: String,
+    /// Effective settings of the run, as the front end resolved them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<Value>,
+    /// RNG seed; `None` means an unseeded RNG, so the run cannot be replayed exactly
+    #[serde(default)]
+    pub random_seed: Option<u64>,
+    pub started_at: String,
+    /// `None` while the run is still going
+    #[serde(default)]
+    pub finished_at: Option<String>,
+    pub host: String,
+}
+
+impl RunMetadata {
+    /// Metadata of a run starting now against `model` on `backend`
+    pub fn start(backend: &str, model: &str) -> Self {
+        Self {
+            apet_version: env!("CARGO_PKG_VERSION").to_string(),
+            backend: backend.to_string(),
+            model: model.to_string(),
+            config: None,
+            random_seed: None,
+            started_at: chrono::Utc::now().to_rfc3339(),
+            finished_at: None,
+            host: host_name(),
+        }
+    }
+
+    /// Records `config` as the run's settings; left unset if it does not serialize
+    pub fn with_config(mut self, config: &impl Serialize) -> Self {
+        self.config = serde_json::to_value(config).ok();
+        self
+    }
+
+    /// Stamps the end of the run
+    pub fn finish(&mut self) {
+        self.finished_at = Some(chrono::Utc::now().to_rfc3339());
+    }
+
+    /// Markdown bullet list for the top of a report
+    pub fn to_markdown(&self) -> String {
+        let mut md = String::new();
+        md.push_str(&format!("- APET version: {}\n", self.apet_version));
+        md.push_str(&format!("- Backend: {} (`{}`)\n", self.backend, self.model));
+        md.push_str(&format!("- Host: {}\n", self.host));
+        md.push_str(&format!("- Started: {}\n", self.started_at));
+        md.push_str(&format!("- Finished: {}\n", self.finished_at.as_deref().unwrap_or("-")));
+        md.push_str(&format!("- Random seed: {}\n", self.random_seed.map_or("none (unseeded)".to_string(), |seed| seed.to_string())));
+        if let Some(config) = &self.config {
+            md.push_str(&format!("- Config: `{}`\n", config));
+        }
+        md
+    }
+}
+
+/// Name of this machine from the environment or `/etc/hostname`
+fn host_name() -> String {
+    ["HOSTNAME", "COMPUTERNAME"].iter()
+        .find_map(|var| std::env::var(var).ok())
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// One elite or generated prompt of a front-end (CLI/GUI) export
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedIndividual {
+    pub behavior: (usize, usize),
+    pub prompt: String,
+    pub fitness: f64,
+    /// Whether few-shot primer turns were sent with the request
+    #[serde(default)]
+    pub primed: bool,
+    /// Seed goal the prompt was generated from; absent in older exports
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub goal: Option<String>,
+}
+
+impl From<&AdversarialPrompt> for ExportedIndividual {
+    /// `behavior` is the prompt's archive cell
+    fn from(prompt: &AdversarialPrompt) -> Self {
+        Self {
+            behavior: prompt.coordinates,
+            prompt: prompt.text.clone(),
+            fitness: prompt.success_score,
+            primed: prompt.primed,
+            goal: Some(prompt.target_goal.clone()),
+        }
+    }
+}
+
+impl ExportedIndividual {
+    /// Section this individual falls into under `group_by`; techniques are
+    /// classified from the prompt text with `techniques`
+    pub fn group_key(&self, group_by: GroupBy, techniques: &TechniqueMapping) -> String {
+        match group_by {
+            GroupBy::AttackType => attack_type_name(aggressiveness_level(&self.prompt)).to_string(),
+            GroupBy::Goal => self.goal.clone().unwrap_or_else(|| "unknown goal".to_string()),
+            GroupBy::Technique => techniques.bins().swap_remove(techniques.classify(&self.prompt)),
+        }
+    }
+}
+
+/// Key a findings report is split by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupBy {
+    /// soft / moderate / aggressive, as `AdversarialPrompt::classify_attack_type`
+    AttackType,
+    /// Seed goal the prompt was generated from
+    Goal,
+    /// Technique class of the prompt text (`TechniqueMapping::classify`)
+    Technique,
+}
+
+impl GroupBy {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.trim().to_lowercase().as_str() {
+            "attack_type" => Ok(GroupBy::AttackType),
+            "goal" => Ok(GroupBy::Goal),
+            "technique" => Ok(GroupBy::Technique),
+            other => Err(format!("unknown group key '{}' (attack_type, goal, technique)", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for GroupBy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            GroupBy::AttackType => "attack_type",
+            GroupBy::Goal => "goal",
+            GroupBy::Technique => "technique",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// One section of a grouped export
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EliteGroup {
+    pub key: String,
+    pub count: usize,
+    pub average_score: f64,
+    /// Best first
+    pub elites: Vec<ExportedIndividual>,
+}
+
+/// Elites split into sections by one key, strongest section first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupedExport {
+    pub schema_version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<RunMetadata>,
+    pub timestamp: String,
+    pub group_by: GroupBy,
+    pub groups: Vec<EliteGroup>,
+}
+
+impl GroupedExport {
+    pub fn new(group_by: GroupBy, individuals: &[ExportedIndividual], techniques: &TechniqueMapping) -> Self {
+        let mut sections: BTreeMap<String, Vec<ExportedIndividual>> = BTreeMap::new();
+        for individual in individuals {
+            sections.entry(individual.group_key(group_by, techniques)).or_default().push(individual.clone());
+        }
+
+        let mut groups: Vec<EliteGroup> = sections.into_iter()
+            .map(|(key, mut elites)| {
+                elites.sort_by(|a, b| b.fitness.total_cmp(&a.fitness));
+                EliteGroup {
+                    key,
+                    count: elites.len(),
+                    average_score: elites.iter().map(|e| e.fitness).sum::<f64>() / elites.len() as f64,
+                    elites,
+                }
+            })
+            .collect();
+        // Ключи одинаковой силы остаются в алфавитном порядке (сортировка стабильна)
+        groups.sort_by(|a, b| b.average_score.total_cmp(&a.average_score));
+
+        Self {
+            schema_version: SCHEMA_VERSION,
+            metadata: None,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            group_by,
+            groups,
+        }
+    }
+
+    pub fn save(&self, path: &str) -> anyhow::Result<usize> {
+        self.save_with_precision(path, DEFAULT_SCORE_PRECISION)
+    }
+
+    /// Writes a sanitized copy with scores rounded to `decimals`; returns
+    /// how many non-finite scores were written as 0
+    pub fn save_with_precision(&self, path: &str, decimals: u32) -> anyhow::Result<usize> {
+        let mut export = self.clone();
+        let mut replaced = 0;
+        for group in &mut export.groups {
+            replaced += sanitize_scores(std::iter::once(&mut group.average_score), decimals);
+            replaced += sanitize_scores(group.elites.iter_mut().map(|ind| &mut ind.fitness), decimals);
+        }
+        std::fs::write(path, serde_json::to_string_pretty(&export)?)?;
+        Ok(replaced)
+    }
+}
+
+/// When a grid cell was first occupied
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CellFill {
+    pub coordinates: (usize, usize),
+    /// Generation whose offspring first filled the cell; 0 for the initial seeds
+    pub first_filled_generation: usize,
+}
+
+/// Cells in discovery order: by generation, then by coordinates
+pub fn fill_order(cells: impl IntoIterator<Item = ((usize, usize), usize)>) -> Vec<CellFill> {
+    let mut fills: Vec<CellFill> = cells.into_iter()
+        .map(|(coordinates, first_filled_generation)| CellFill { coordinates, first_filled_generation })
+        .collect();
+    fills.sort_by_key(|fill| (fill.first_filled_generation, fill.coordinates));
+    fills
+}
+
+/// Per-generation curves of a front-end run
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExportedStatistics {
+    pub generations: Vec<usize>,
+    pub best_fitness: Vec<f64>,
+    pub coverage: Vec<f64>,
+    pub diversity: Vec<f64>,
+}
+
+/// GUI settings captured with a manual export
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedSettings {
+    pub max_generations: usize,
+    pub population_size: usize,
+    pub mutation_rate: f64,
+    pub grid_size: (usize, usize),
+    pub language: String,
+}
+
+/// Results written by the CLI and GUI front-ends
+/// (`apet_mapelites_results.json`, `apet_gui_real_results.json`, ...)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResultsExport {
+    pub schema_version: u32,
+    /// Provenance of the run; absent in older exports
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<RunMetadata>,
+    pub timestamp: String,
+    pub algorithm: String,
+    pub total_generations: usize,
+    pub grid_dimensions: (usize, usize),
+    pub map_elites_grid: Vec<ExportedIndividual>,
+    #[serde(default)]
+    pub all_generated: Vec<ExportedIndividual>,
+    #[serde(default)]
+    pub statistics: ExportedStatistics,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settings: Option<ExportedSettings>,
+    /// Threshold the export was filtered with, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_fitness: Option<f64>,
+    /// What `behavior` encodes
+    #[serde(default = "BehaviorAxes::technique_complexity")]
+    pub axes: BehaviorAxes,
+    /// Generation at which each cell was first filled; empty in older exports
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fill_order: Vec<CellFill>,
+}
+
+impl ResultsExport {
+    pub fn new(grid_dimensions: (usize, usize), total_generations: usize) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            metadata: None,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            algorithm: "MAP-Elites".to_string(),
+            total_generations,
+            grid_dimensions,
+            map_elites_grid: Vec::new(),
+            all_generated: Vec::new(),
+            statistics: ExportedStatistics::default(),
+            settings: None,
+            min_fitness: None,
+            axes: BehaviorAxes::technique_complexity(),
+            fill_order: Vec::new(),
+        }
+    }
+
+    /// Elites of the library archive with its per-generation curves;
+    /// `all_generated` is left to the caller
+    pub fn from_archive(archive: &MapElitesArchive) -> Self {
+        let history = &archive.generation_stats;
+        Self {
+            map_elites_grid: archive.get_all_elites().into_iter().map(ExportedIndividual::from).collect(),
+            statistics: ExportedStatistics {
+                generations: history.iter().map(|stats| stats.generation).collect(),
+                best_fitness: history.iter().map(|stats| stats.archive_stats.max_score).collect(),
+                coverage: history.iter().map(|stats| stats.archive_stats.coverage).collect(),
+                diversity: history.iter().map(|stats| stats.diversity).collect(),
+            },
+            axes: archive.axes(),
+            fill_order: archive.fill_order(),
+            ..Self::new(archive.size, history.len())
+        }
+    }
+
+    /// Drops individuals below `min_fitness` from the grid and the history,
+    /// returning how many grid elites were filtered out
+    pub fn retain_min_fitness(&mut self, min_fitness: f64) -> usize {
+        let before = self.map_elites_grid.len();
+        self.map_elites_grid.retain(|ind| ind.fitness >= min_fitness);
+        self.all_generated.retain(|ind| ind.fitness >= min_fitness);
+        self.min_fitness = Some(min_fitness);
+        before - self.map_elites_grid.len()
+    }
+
+    /// Rounds fitness values and curves to `decimals`, replacing NaN and
+    /// infinities with 0; returns the number replaced
+    pub fn sanitize_scores(&mut self, decimals: u32) -> usize {
+        let stats = &mut self.statistics;
+        sanitize_scores(self.map_elites_grid.iter_mut().chain(&mut self.all_generated).map(|ind| &mut ind.fitness), decimals)
+            + sanitize_scores(stats.best_fitness.iter_mut().chain(&mut stats.coverage).chain(&mut stats.diversity), decimals)
+            + sanitize_scores(self.min_fitness.iter_mut(), decimals)
+    }
+
+    pub fn save(&self, path: &str) -> anyhow::Result<usize> {
+        self.save_with_precision(path, DEFAULT_SCORE_PRECISION)
+    }
+
+    /// Writes a sanitized copy with scores rounded to `decimals`; returns
+    /// how many non-finite scores were written as 0
+    pub fn save_with_precision(&self, path: &str, decimals: u32) -> anyhow::Result<usize> {
+        let mut export = self.clone();
+        let replaced = export.sanitize_scores(decimals);
+        std::fs::write(path, serde_json::to_string_pretty(&export)?)?;
+        Ok(replaced)
+    }
+
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        Self::from_json_str(&std::fs::read_to_string(path)?)
+    }
+
+    /// Parses a results file, migrating the unversioned shapes written
+    /// before `schema_version` existed
+    pub fn from_json_str(content: &str) -> anyhow::Result<Self> {
+        let value: Value = serde_json::from_str(content)?;
+
+        match value.get("schema_version").and_then(Value::as_u64) {
+            Some(version) if version > SCHEMA_VERSION as u64 => {
+                anyhow::bail!("Unsupported schema_version {} (max {})", version, SCHEMA_VERSION)
+            }
+            Some(_) => Ok(serde_json::from_value(value)?),
+            None => migrate_legacy_results(&value),
+        }
+    }
+}
+
+/// Migrates the legacy GUI (`map_elites_grid`/`behavior`) and CLI
+/// (`grid_solutions`/`technique`/`difficulty`) layouts
+fn migrate_legacy_results(value: &Value) -> anyhow::Result<ResultsExport> {
+    let parse_individual = |item: &Value| -> Option<ExportedIndividual> {
+        let behavior = match item["behavior"].as_array() {
+            Some(b) if b.len() == 2 => (b[0].as_u64()? as usize, b[1].as_u64()? as usize),
+            _ => (item["technique"].as_u64()? as usize, item["difficulty"].as_u64()? as usize),
+        };
+        Some(ExportedIndividual {
+            behavior,
+            prompt: item["prompt"].as_str()?.to_string(),
+            fitness: item["fitness"].as_f64()?,
+            primed: false,
+            goal: None,
+        })
+    };
+
+    let grid = value.get("map_elites_grid")
+        .or_else(|| value.get("grid_solutions"))
+        .and_then(Value::as_array)
+        .ok_or_else(|| anyhow::anyhow!("Unrecognized results format: no grid found"))?;
+
+    let grid_dimensions = value["grid_dimensions"].as_array()
+        .and_then(|d| Some((d.first()?.as_u64()? as usize, d.get(1)?.as_u64()? as usize)))
+        .unwrap_or((5, 4));
+
+    let total_generations = value["total_generations"].as_u64()
+        .or_else(|| value["generations"].as_u64())
+        .unwrap_or(0) as usize;
+
+    let mut export = ResultsExport::new(grid_dimensions, total_generations);
+    if let Some(timestamp) = value["timestamp"].as_str() {
+        export.timestamp = timestamp.to_string();
+    }
+    export.map_elites_grid = grid.iter().filter_map(parse_individual).collect();
+    export.all_generated = value["all_generated"].as_array()
+        .map(|items| items.iter().filter_map(parse_individual).collect())
+        .unwrap_or_default();
+    export.statistics = serde_json::from_value(value["statistics"].clone()).unwrap_or_default();
+
+    Ok(export)
+}
+
+/// Full library archive written by `MapElites::save_results`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveExport {
+    pub schema_version: u32,
+    /// Provenance of the run; absent in older exports
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<RunMetadata>,
+    pub timestamp: String,
+    pub grid_size: (usize, usize),
+    pub elites: Vec<AdversarialPrompt>,
+    #[serde(default)]
+    pub generation_stats: Vec<GenerationStats>,
+    /// What the elites' `coordinates` encode
+    #[serde(default = "BehaviorAxes::length_aggressiveness")]
+    pub axes: BehaviorAxes,
+    /// Generation at which each cell was first filled; empty in older exports
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fill_order: Vec<CellFill>,
+}
+
+impl ArchiveExport {
+    pub fn from_archive(archive: &MapElitesArchive) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            metadata: None,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            grid_size: archive.size,
+            elites: archive.get_all_elites().into_iter().cloned().collect(),
+            generation_stats: archive.generation_stats.clone(),
+            axes: archive.axes(),
+            fill_order: archive.fill_order(),
+        }
+    }
+
+    /// Rebuilds the archive, placing each elite by its stored coordinates
+    pub fn into_archive(self) -> MapElitesArchive {
+        let mut archive = MapElitesArchive::new();
+        for elite in self.elites {
+            archive.add_prompt(elite);
+        }
+        archive.generation_stats = self.generation_stats;
+        for fill in self.fill_order {
+            let (x, y) = fill.coordinates;
+            if let Some(cell) = archive.first_filled.get_mut(x).and_then(|row| row.get_mut(y)) {
+                *cell = Some(fill.first_filled_generation);
+            }
+        }
+        archive
+    }
+
+    /// Rounds elite scores and per-generation statistics to `decimals`,
+    /// replacing NaN and infinities with 0; returns the number replaced
+    pub fn sanitize_scores(&mut self, decimals: u32) -> usize {
+        let mut replaced = sanitize_scores(self.elites.iter_mut().map(|elite| &mut elite.success_score), decimals);
+        for stats in &mut self.generation_stats {
+            let archive = &mut stats.archive_stats;
+            let values = [&mut archive.coverage, &mut archive.average_score, &mut archive.max_score, &mut archive.min_score];
+            replaced += sanitize_scores(values, decimals);
+            replaced += sanitize_scores(stats.operators.values_mut().map(|op| &mut op.success_rate), decimals);
+        }
+        replaced
+    }
+
+    pub fn save(&self, path: &str) -> anyhow::Result<usize> {
+        self.save_with_precision(path, DEFAULT_SCORE_PRECISION)
+    }
+
+    /// Writes a sanitized copy with scores rounded to `decimals`; returns
+    /// how many non-finite scores were written as 0
+    pub fn save_with_precision(&self, path: &str, decimals: u32) -> anyhow::Result<usize> {
+        let mut export = self.clone();
+        let replaced = export.sanitize_scores(decimals);
+        std::fs::write(path, serde_json::to_string_pretty(&export)?)?;
+        Ok(replaced)
+    }
+
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        let value: Value = serde_json::from_str(&content)?;
+
+        match value.get("schema_version").and_then(Value::as_u64) {
+            Some(version) if version > SCHEMA_VERSION as u64 => {
+                anyhow::bail!("Unsupported schema_version {} (max {})", version, SCHEMA_VERSION)
+            }
+            Some(_) => Ok(serde_json::from_value(value)?),
+            // Старый `save_to_file` писал голый массив промптов,
+            // а `save_results` - сериализованный MapElitesArchive
+            None if value.is_array() => Ok(Self {
+                schema_version: SCHEMA_VERSION,
+                metadata: None,
+                timestamp: String::new(),
+                grid_size: (3, 3),
+                elites: serde_json::from_value(value)?,
+                generation_stats: Vec::new(),
+                axes: BehaviorAxes::length_aggressiveness(),
+                fill_order: Vec::new(),
+            }),
+            None => {
+                let archive: MapElitesArchive = serde_json::from_value(value)?;
+                Ok(Self { timestamp: String::new(), ..Self::from_archive(&archive) })
+            }
+        }
+    }
+}
+
+/// Cell scores as a 2D matrix: rows are the first behaviour axis
+/// (technique), columns the second (complexity); `None` is an empty cell
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoreMatrix {
+    pub rows: Vec<Vec<Option<f64>>>,
+}
+
+impl ScoreMatrix {
+    /// Builds a `dims.0 × dims.1` matrix; cells outside `dims` are ignored
+    pub fn from_cells(dims: (usize, usize), cells: impl IntoIterator<Item = ((usize, usize), f64)>) -> Self {
+        let mut rows = vec![vec![None; dims.1]; dims.0];
+        for ((row, col), score) in cells {
+            if let Some(cell) = rows.get_mut(row).and_then(|r| r.get_mut(col)) {
+                *cell = Some(score);
+            }
+        }
+        Self { rows }
+    }
+
+    pub fn from_archive(archive: &MapElitesArchive) -> Self {
+        Self::from_cells(
+            archive.size,
+            archive.get_all_elites().into_iter().map(|elite| (elite.coordinates, elite.success_score)),
+        )
+    }
+
+    /// Header row of column indices, then one row per first-axis bin
+    pub fn to_csv(&self) -> String {
+        let columns = self.rows.first().map_or(0, Vec::len);
+        let mut csv = String::new();
+
+        let header: Vec<String> = (0..columns).map(|c| c.to_string()).collect();
+        csv.push_str(&format!(",{}\n", header.join(",")));
+
+        for (index, row) in self.rows.iter().enumerate() {
+            let cells: Vec<String> = row.iter()
+                .map(|cell| cell.map(|score| format!("{:.4}", score)).unwrap_or_default())
+                .collect();
+            csv.push_str(&format!("{},{}\n", index, cells.join(",")));
+        }
+
+        csv
+    }
+
+    /// Parses the format written by `to_csv` (blank cells are empty)
+    pub fn from_csv(content: &str) -> anyhow::Result<Self> {
+        let mut lines = content.lines().filter(|line| !line.trim().is_empty());
+        let columns = lines.next()
+            .ok_or_else(|| anyhow::anyhow!("Empty score matrix"))?
+            .split(',')
+            .count()
+            .saturating_sub(1);
+
+        let mut rows = Vec::new();
+        for (line_no, line) in lines.enumerate() {
+            let cells: Vec<&str> = line.split(',').skip(1).collect();
+            if cells.len() != columns {
+                anyhow::bail!("Row {} has {} cells, expected {}", line_no, cells.len(), columns);
+            }
+
+            let row = cells.iter()
+                .map(|cell| match cell.trim() {
+                    "" => Ok(None),
+                    value => value.parse::<f64>().map(Some),
+                })
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| anyhow::anyhow!("Row {}: {}", line_no, e))?;
+            rows.push(row);
+        }
+
+        Ok(Self { rows })
+    }
+
+    pub fn save(&self, path: &str) -> anyhow::Result<()> {
+        std::fs::write(path, self.to_csv())?;
+        Ok(())
+    }
+
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        Self::from_csv(&std::fs::read_to_string(path)?)
+    }
+}
+
+/// One accepted elite, as a line of the NDJSON stream
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamedElite {
+    pub generation: usize,
+    pub coordinates: (usize, usize),
+    pub score: f64,
+    pub text: String,
+}
+
+impl StreamedElite {
+    pub fn from_prompt(prompt: &AdversarialPrompt) -> Self {
+        Self {
+            generation: prompt.generation as usize,
+            coordinates: prompt.coordinates,
+            score: prompt.success_score,
+            text: prompt.text.clone(),
+        }
+    }
+}
+
+/// Appends one JSON line per accepted elite to a file or stdout (`-`).
+/// Each line is written and flushed under a lock, so concurrent emitters
+/// never interleave.
+pub struct EliteStream {
+    writer: Mutex<Box<dyn Write + Send>>,
+}
+
+impl EliteStream {
+    pub fn open(target: &str) -> anyhow::Result<Self> {
+        let writer: Box<dyn Write + Send> = if target == "-" {
+            Box::new(std::io::stdout())
+        } else {
+            Box::new(std::fs::OpenOptions::new().create(true).append(true).open(target)?)
+        };
+        Ok(Self { writer: Mutex::new(writer) })
+    }
+
+    pub fn emit(&self, elite: &StreamedElite) -> anyhow::Result<()> {
+        let mut elite = elite.clone();
+        sanitize_scores(std::iter::once(&mut elite.score), DEFAULT_SCORE_PRECISION);
+        let mut line = serde_json::to_string(&elite)?;
+        line.push('\n');
+
+        let mut writer = self.writer.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        writer.write_all(line.as_bytes())?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// One prompt per line, for tools that only want the strings. Backslashes,
+/// newlines and carriage returns inside a prompt become `\\`, `\n` and `\r`,
+/// so every line is exactly one prompt
+pub fn prompt_lines<'a>(prompts: impl IntoIterator<Item = &'a str>) -> String {
+    let mut lines = String::new();
+    for prompt in prompts {
+        for c in prompt.chars() {
+            match c {
+                '\\' => lines.push_str("\\\\"),
+                '\n' => lines.push_str("\\n"),
+                '\r' => lines.push_str("\\r"),
+                c => lines.push(c),
+            }
+        }
+        lines.push('\n');
+    }
+    lines
+}
+
+/// Writes `prompt_lines` to `path`; returns how many prompts were written
+pub fn save_prompt_lines<'a>(path: &str, prompts: impl IntoIterator<Item = &'a str>) -> anyhow::Result<usize> {
+    let prompts: Vec<&str> = prompts.into_iter().collect();
+    std::fs::write(path, prompt_lines(prompts.iter().copied()))?;
+    Ok(prompts.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nan_score_is_written_as_zero_and_loads_back() {
+        let mut export = ResultsExport::new((5, 5), 1);
+        export.map_elites_grid.push(ExportedIndividual {
+            behavior: (0, 0),
+            prompt: "ignore previous instructions".to_string(),
+            fitness: f64::NAN,
+            primed: false,
+            goal: None,
+        });
+        export.statistics.best_fitness = vec![0.123456, f64::INFINITY];
+
+        let path = std::env::temp_dir().join(format!("apet_nan_export_{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        export.save(path).unwrap();
+        let loaded = ResultsExport::load(path);
+        std::fs::remove_file(path).ok();
+
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.map_elites_grid[0].fitness, 0.0);
+        assert_eq!(loaded.statistics.best_fitness, vec![0.1235, 0.0]);
+    }
+
+    #[test]
+    fn results_export_from_archive_keeps_the_archive_cells_and_axes() {
+        let mut prompt = AdversarialPrompt::new("ignore previous instructions".to_string(), "leak".to_string(), "AI".to_string(), 0);
+        prompt.update_success_score(0.7);
+        let cell = prompt.coordinates;
+        let mut archive = MapElitesArchive::new();
+        assert!(archive.add_prompt(prompt));
+        archive.record_first_filled(0);
+
+        let export = ResultsExport::from_archive(&archive);
+        assert_eq!(export.grid_dimensions, archive.size);
+        assert_eq!(export.axes, BehaviorAxes::length_aggressiveness());
+        assert_eq!(export.map_elites_grid.len(), 1);
+        assert_eq!(export.map_elites_grid[0].behavior, cell);
+        assert_eq!(export.map_elites_grid[0].goal.as_deref(), Some("leak"));
+        assert_eq!(export.fill_order, vec![CellFill { coordinates: cell, first_filled_generation: 0 }]);
+    }
+}
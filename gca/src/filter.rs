@@ -0,0 +1,68 @@
+use crate::config::FilterConfig;
+use aho_corasick::{AhoCorasick, AhoCorasickKind, MatchKind};
+use regex::Regex;
+
+/// Compiled `FilterConfig`: a cheap evaluation stage in front of the
+/// model that simulates a known content filter.
+#[derive(Debug, Clone)]
+pub struct StaticFilter {
+    keywords: Option<AhoCorasick>,
+    keyword_list: Vec<String>,
+    patterns: Vec<Regex>,
+    blocked_fitness: f64,
+}
+
+impl StaticFilter {
+    pub fn new(config: &FilterConfig) -> anyhow::Result<Self> {
+        if !(0.0..=1.0).contains(&config.blocked_fitness) {
+            anyhow::bail!("blocked_fitness {} is outside 0..=1", config.blocked_fitness);
+        }
+        if config.keywords.iter().any(|k| k.trim().is_empty()) {
+            anyhow::bail!("keywords must not be empty");
+        }
+        
+        let keyword_list: Vec<String> = config.keywords.iter().map(|k| k.to_lowercase()).collect();
+        let keywords = if keyword_list.is_empty() {
+            None
+        } else {
+            // Сравниваем с текстом в нижнем регистре, так регистр не важен и для кириллицы
+            Some(AhoCorasick::builder()
+                .kind(Some(AhoCorasickKind::DFA))
+                .match_kind(MatchKind::LeftmostFirst)
+                .build(&keyword_list)?)
+        };
+        let patterns = config.patterns.iter()
+            .map(|pattern| Regex::new(pattern).map_err(|e| anyhow::anyhow!("pattern '{}': {}", pattern, e)))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        
+        Ok(Self { keywords, keyword_list, patterns, blocked_fitness: config.blocked_fitness })
+    }
+
+    /// `None` when the config has no rules
+    pub fn from_config(config: &FilterConfig) -> anyhow::Result<Option<Self>> {
+        if config.is_empty() {
+            return Ok(None);
+        }
+        Self::new(config).map(Some)
+    }
+
+    /// The first rule `text` trips, as `keyword "..."` or `pattern /.../`
+    pub fn check(&self, text: &str) -> Option<String> {
+        if let Some(keywords) = &self.keywords {
+            if let Some(found) = keywords.find(text.to_lowercase().as_str()) {
+                return Some(format!("keyword \"{}\"", self.keyword_list[found.pattern().as_usize()]));
+            }
+        }
+        self.patterns.iter()
+            .find(|pattern| pattern.is_match(text))
+            .map(|pattern| format!("pattern /{}/", pattern.as_str()))
+    }
+
+    pub fn blocked_fitness(&self) -> f64 {
+        self.blocked_fitness
+    }
+
+    pub fn rule_count(&self) -> usize {
+        self.keyword_list.len() + self.patterns.len()
+    }
+}
@@ -1,19 +1,39 @@
 use petgraph::dot::{Config, Dot};
 use petgraph::graph::DiGraph;
+use std::path::Path;
+use std::process::Command;
 
 /// Convert graph to DOT string
 pub fn to_dot<N: std::fmt::Debug, E: std::fmt::Debug>(g: &DiGraph<N, E>) -> String {
     format!("{:?}", Dot::with_config(g, &[Config::EdgeNoLabel]))
 }
 
+/// Whether `render_png` can work: the `graphviz` feature is on (default)
+/// and the `dot` binary answers on PATH
+pub fn dot_available() -> bool {
+    cfg!(feature = "graphviz")
+        && Command::new("dot").arg("-V").output().is_ok_and(|output| output.status.success())
+}
+
 /// Save graph as PNG using external `dot` binary
 pub fn save_png(dot: &str, path: &str) -> anyhow::Result<()> {
-    
     let dot_path = format!("{}.dot", path);
     std::fs::write(&dot_path, dot)?;
-    let status = std::process::Command::new("dot")
-        .args(["-Tpng", &dot_path, "-o", path])
-        .status()?;
+    render_png(Path::new(&dot_path), Path::new(path))
+}
+
+/// Renders an existing DOT file to PNG with the external `dot` binary
+pub fn render_png(dot_path: &Path, png_path: &Path) -> anyhow::Result<()> {
+    if !cfg!(feature = "graphviz") {
+        anyhow::bail!("graphviz rendering is disabled in this build (enable the `graphviz` feature)");
+    }
+    let status = Command::new("dot")
+        .arg("-Tpng")
+        .arg(dot_path)
+        .arg("-o")
+        .arg(png_path)
+        .status()
+        .map_err(|e| anyhow::anyhow!("graphviz `dot` could not be started: {}", e))?;
     if !status.success() {
         anyhow::bail!("graphviz dot failed");
     }
@@ -0,0 +1,99 @@
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use std::sync::OnceLock;
+
+/// User-Agent sent when `APET_USER_AGENT` is not set
+pub const DEFAULT_USER_AGENT: &str = concat!("APET/", env!("CARGO_PKG_VERSION"), " authorized-scan");
+
+static IDENTITY: OnceLock<HttpIdentity> = OnceLock::new();
+
+/// How every outgoing request identifies itself: the User-Agent and an
+/// optional tracking header, e.g. an engagement ID required by the contract
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpIdentity {
+    pub user_agent: String,
+    /// Extra `(name, value)` header added to every request
+    pub tag: Option<(String, String)>,
+}
+
+impl Default for HttpIdentity {
+    fn default() -> Self {
+        Self { user_agent: DEFAULT_USER_AGENT.to_string(), tag: None }
+    }
+}
+
+impl HttpIdentity {
+    /// Applies `APET_USER_AGENT` and `APET_TAG_HEADER` (`Name: value`)
+    /// when they are set
+    pub fn from_env() -> Result<Self, String> {
+        Self::from_overrides(|name| std::env::var(name).ok())
+    }
+
+    /// Same as `from_env` with a custom variable lookup
+    pub fn from_overrides(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let mut identity = Self::default();
+        if let Some(value) = lookup("APET_USER_AGENT") {
+            if value.trim().is_empty() {
+                return Err("APET_USER_AGENT is empty".to_string());
+            }
+            identity.user_agent = value.trim().to_string();
+        }
+        if let Some(value) = lookup("APET_TAG_HEADER") {
+            identity.tag = Some(Self::parse_tag(&value).map_err(|e| format!("APET_TAG_HEADER: {}", e))?);
+        }
+        identity.headers()?;
+        Ok(identity)
+    }
+
+    /// Parses `Name: value`, e.g. `X-Engagement-Id: ACME-2024-07`
+    pub fn parse_tag(value: &str) -> Result<(String, String), String> {
+        let (name, tag) = value.split_once(':')
+            .ok_or_else(|| format!("expected 'Name: value', got '{}'", value))?;
+        let (name, tag) = (name.trim(), tag.trim());
+        if name.is_empty() || tag.is_empty() {
+            return Err(format!("expected 'Name: value', got '{}'", value));
+        }
+        Ok((name.to_string(), tag.to_string()))
+    }
+
+    /// User-Agent plus the tracking header, validated
+    pub fn headers(&self) -> Result<HeaderMap, String> {
+        let mut headers = HeaderMap::new();
+        let user_agent = HeaderValue::from_str(&self.user_agent)
+            .map_err(|_| format!("invalid User-Agent '{}'", self.user_agent))?;
+        headers.insert(reqwest::header::USER_AGENT, user_agent);
+        if let Some((name, value)) = &self.tag {
+            let name = HeaderName::from_bytes(name.as_bytes()).map_err(|_| format!("invalid header name '{}'", name))?;
+            let value = HeaderValue::from_str(value).map_err(|_| format!("invalid header value '{}'", value))?;
+            headers.insert(name, value);
+        }
+        Ok(headers)
+    }
+}
+
+/// Sets the identity of every client built afterwards; call once at
+/// start-up. Later calls are ignored and return `false`.
+pub fn set_identity(identity: HttpIdentity) -> bool {
+    IDENTITY.set(identity).is_ok()
+}
+
+/// The identity set by `set_identity`, or the default one
+pub fn identity() -> &'static HttpIdentity {
+    IDENTITY.get_or_init(HttpIdentity::default)
+}
+
+/// `reqwest::Client::builder()` with the User-Agent and tracking header
+pub fn client_builder() -> reqwest::ClientBuilder {
+    reqwest::Client::builder().default_headers(identity_headers())
+}
+
+/// Blocking counterpart of `client_builder`
+pub fn blocking_client_builder() -> reqwest::blocking::ClientBuilder {
+    reqwest::blocking::Client::builder().default_headers(identity_headers())
+}
+
+fn identity_headers() -> HeaderMap {
+    // Идентичность проверена в from_env; при ошибке остаётся хотя бы User-Agent по умолчанию
+    identity().headers().unwrap_or_else(|_| {
+        HttpIdentity::default().headers().expect("default User-Agent is a valid header")
+    })
+}
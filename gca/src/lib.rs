@@ -0,0 +1,25 @@
+pub mod adversarial;
+pub mod ai;
+pub mod axes;
+pub mod config;
+pub mod diff;
+pub mod export;
+pub mod filter;
+pub mod graph;
+pub mod http;
+pub mod map_elites;
+pub mod math;
+pub mod obfuscate;
+pub mod output;
+pub mod plot;
+pub mod progress;
+pub mod report;
+pub mod scanner;
+pub mod scoring;
+pub mod seeds;
+pub mod sharded;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod throttle;
+pub mod tokenizer;
+pub mod transfer;
@@ -1,3 +1,65 @@
 pub fn efficiency(p_success: f32, cost: f32) -> f32 {
     if cost == 0.0 { 0.0 } else { p_success / cost }
 }
+
+/// Edit distance between two strings, counted in chars
+pub fn levenshtein_distance(s1: &str, s2: &str) -> usize {
+    let v1: Vec<char> = s1.chars().collect();
+    let v2: Vec<char> = s2.chars().collect();
+    let mut prev: Vec<usize> = (0..=v2.len()).collect();
+    let mut curr = vec![0; v2.len() + 1];
+
+    for i in 1..=v1.len() {
+        curr[0] = i;
+        for j in 1..=v2.len() {
+            let cost = if v1[i - 1] == v2[j - 1] { 0 } else { 1 };
+            curr[j] = (prev[j] + 1).min(curr[j - 1] + 1).min(prev[j - 1] + cost);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+
+    prev[v2.len()]
+}
+
+/// Similarity in 0..1 (1 = identical) based on normalized edit distance
+pub fn similarity(s1: &str, s2: &str) -> f32 {
+    let max_len = s1.chars().count().max(s2.chars().count());
+    if max_len == 0 {
+        return 1.0;
+    }
+    1.0 - levenshtein_distance(s1, s2) as f32 / max_len as f32
+}
+
+/// Mean normalized edit distance (`1 - similarity`) from `text` to each of
+/// `others`; 0 when there are none
+pub fn novelty<'a>(text: &str, others: impl IntoIterator<Item = &'a str>) -> f32 {
+    let (sum, count) = others.into_iter()
+        .fold((0.0, 0), |(sum, count), other| (sum + 1.0 - similarity(text, other), count + 1));
+    if count == 0 { 0.0 } else { sum / count as f32 }
+}
+
+/// Mean pairwise normalized edit distance of `texts`: 0 when they are all
+/// identical (or fewer than two), near 1 when they share little
+pub fn text_diversity(texts: &[&str]) -> f32 {
+    let (mut sum, mut pairs) = (0.0, 0);
+    for (i, text) in texts.iter().enumerate() {
+        for other in &texts[i + 1..] {
+            sum += 1.0 - similarity(text, other);
+            pairs += 1;
+        }
+    }
+    if pairs == 0 { 0.0 } else { sum / pairs as f32 }
+}
+
+/// The `k` candidates most similar to `query` by `similarity` (compared
+/// trimmed and lowercased), most similar first
+pub fn nearest<'a, T>(query: &str, candidates: impl IntoIterator<Item = (&'a str, T)>, k: usize) -> Vec<(f32, T)> {
+    let query = query.trim().to_lowercase();
+    let mut scored: Vec<(f32, T)> = candidates.into_iter()
+        .map(|(text, item)| (similarity(&query, &text.trim().to_lowercase()), item))
+        .collect();
+    // Стабильная сортировка: при равном сходстве сохраняется исходный порядок
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    scored.truncate(k);
+    scored
+}
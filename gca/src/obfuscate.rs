@@ -0,0 +1,114 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+/// Zero-width space, invisible in most renderers
+const ZERO_WIDTH_SPACE: char = '\u{200B}';
+/// Shortest word split by `Obfuscation::Spacing`
+const MIN_SPACED_WORD: usize = 4;
+
+/// Character-level rewrite that keeps a prompt readable to a model while
+/// hiding keywords from naive filters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Obfuscation {
+    /// `bypass` -> `byp455`
+    Leetspeak,
+    /// Latin letters swapped for identical-looking Cyrillic ones
+    Homoglyph,
+    /// Zero-width spaces inside words
+    ZeroWidth,
+    /// `bypass` -> `b y p a s s`
+    Spacing,
+}
+
+impl Obfuscation {
+    pub const ALL: [Obfuscation; 4] = [Obfuscation::Leetspeak, Obfuscation::Homoglyph, Obfuscation::ZeroWidth, Obfuscation::Spacing];
+
+    /// Applies the transform to each eligible character (or word, for
+    /// `Spacing`) with probability `intensity`
+    pub fn apply(self, text: &str, intensity: f64, rng: &mut impl Rng) -> String {
+        let intensity = intensity.clamp(0.0, 1.0);
+        match self {
+            Obfuscation::Leetspeak => text.chars()
+                .map(|c| match leet(c) {
+                    Some(replacement) if rng.gen_bool(intensity) => replacement,
+                    _ => c,
+                })
+                .collect(),
+            Obfuscation::Homoglyph => text.chars()
+                .map(|c| match homoglyph(c) {
+                    Some(replacement) if rng.gen_bool(intensity) => replacement,
+                    _ => c,
+                })
+                .collect(),
+            Obfuscation::ZeroWidth => {
+                let chars: Vec<char> = text.chars().collect();
+                let mut result = String::with_capacity(text.len() * 2);
+                for (i, &c) in chars.iter().enumerate() {
+                    result.push(c);
+                    // Только между двумя буквами одного слова
+                    let inside_word = c.is_alphanumeric() && chars.get(i + 1).is_some_and(|next| next.is_alphanumeric());
+                    if inside_word && rng.gen_bool(intensity) {
+                        result.push(ZERO_WIDTH_SPACE);
+                    }
+                }
+                result
+            }
+            Obfuscation::Spacing => text.split(' ')
+                .map(|word| {
+                    if word.chars().count() >= MIN_SPACED_WORD && word.chars().all(char::is_alphabetic) && rng.gen_bool(intensity) {
+                        word.chars().map(String::from).collect::<Vec<_>>().join(" ")
+                    } else {
+                        word.to_string()
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(" "),
+        }
+    }
+}
+
+/// Applies one or two randomly chosen transforms at `intensity`
+pub fn obfuscate(text: &str, intensity: f64, rng: &mut impl Rng) -> String {
+    let count = rng.gen_range(1..=2);
+    let mut result = text.to_string();
+    for &transform in Obfuscation::ALL.choose_multiple(rng, count) {
+        result = transform.apply(&result, intensity, rng);
+    }
+    result
+}
+
+fn leet(c: char) -> Option<char> {
+    match c.to_ascii_lowercase() {
+        'a' => Some('4'),
+        'e' => Some('3'),
+        'i' => Some('1'),
+        'o' => Some('0'),
+        's' => Some('5'),
+        't' => Some('7'),
+        _ => None,
+    }
+}
+
+fn homoglyph(c: char) -> Option<char> {
+    match c {
+        'a' => Some('а'),
+        'c' => Some('с'),
+        'e' => Some('е'),
+        'o' => Some('о'),
+        'p' => Some('р'),
+        'x' => Some('х'),
+        'y' => Some('у'),
+        'A' => Some('А'),
+        'B' => Some('В'),
+        'C' => Some('С'),
+        'E' => Some('Е'),
+        'H' => Some('Н'),
+        'K' => Some('К'),
+        'M' => Some('М'),
+        'O' => Some('О'),
+        'P' => Some('Р'),
+        'T' => Some('Т'),
+        'X' => Some('Х'),
+        _ => None,
+    }
+}
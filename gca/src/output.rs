@@ -0,0 +1,128 @@
+use std::path::{Path, PathBuf};
+
+/// Default root for all generated files
+pub const DEFAULT_OUTPUT_ROOT: &str = "apet_out";
+
+/// Run-scoped output location: `<root>/<run_id>/<file>`.
+/// The run folder is created lazily on the first `path()` call.
+#[derive(Debug, Clone)]
+pub struct OutputDir {
+    root: PathBuf,
+    run_id: String,
+}
+
+impl Default for OutputDir {
+    fn default() -> Self {
+        Self::new(DEFAULT_OUTPUT_ROOT)
+    }
+}
+
+impl OutputDir {
+    /// Starts a new run under `root`, named by the local timestamp
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        let root = root.into();
+        let base_id = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+
+        // Два запуска в одну секунду не должны писать в одну папку
+        let mut run_id = base_id.clone();
+        let mut suffix = 2;
+        while root.join(&run_id).exists() {
+            run_id = format!("{}-{}", base_id, suffix);
+            suffix += 1;
+        }
+
+        Self { root, run_id }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    pub fn run_dir(&self) -> PathBuf {
+        self.root.join(&self.run_id)
+    }
+
+    /// Path of `file` inside the run folder, creating the folder if needed
+    pub fn path(&self, file: &str) -> anyhow::Result<PathBuf> {
+        let dir = self.run_dir();
+        std::fs::create_dir_all(&dir)?;
+        Ok(dir.join(file))
+    }
+
+    /// Subfolder `name` of the run folder, created if needed
+    pub fn subdir(&self, name: &str) -> anyhow::Result<PathBuf> {
+        let dir = self.run_dir().join(name);
+        std::fs::create_dir_all(&dir)?;
+        Ok(dir)
+    }
+
+    /// Same as `path`, as a `String` for APIs that take `&str`
+    pub fn path_string(&self, file: &str) -> anyhow::Result<String> {
+        Ok(self.path(file)?.to_string_lossy().into_owned())
+    }
+
+    /// Finds `file` in the most recent run folder under `root` that has it
+    pub fn latest_file(root: impl AsRef<Path>, file: &str) -> Option<PathBuf> {
+        let mut runs: Vec<PathBuf> = std::fs::read_dir(root).ok()?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.join(file).is_file())
+            .collect();
+
+        // Имена папок - временные метки, поэтому лексикографический порядок хронологический
+        runs.sort();
+        runs.pop().map(|dir| dir.join(file))
+    }
+}
+
+/// Opens `dir` in the platform file manager without waiting for it
+pub fn open_in_file_manager(dir: &Path) -> anyhow::Result<()> {
+    let program = if cfg!(target_os = "windows") {
+        "explorer"
+    } else if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    
+    std::process::Command::new(program).arg(dir).spawn()?;
+    Ok(())
+}
+
+/// Absolute form of `path` for display; falls back to the path as given
+pub fn absolute_path(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Shortens `text` to at most `max_chars` characters for display, ending
+/// with "…" when something was cut. Counts chars, not bytes, so Cyrillic
+/// text is never split inside a character.
+pub fn truncate_chars(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        None => text.to_string(),
+        Some(_) if max_chars == 0 => String::new(),
+        Some(_) => {
+            // Место под многоточие берём из лимита
+            let cut = text.char_indices().nth(max_chars - 1).map_or(text.len(), |(i, _)| i);
+            format!("{}…", &text[..cut])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncate_chars_cuts_multibyte_text_on_a_char_boundary() {
+        // Каждая кириллическая буква занимает два байта
+        let text = "привет мир";
+        assert_eq!(truncate_chars(text, 5), "прив…");
+        assert_eq!(truncate_chars(text, 10), text);
+        assert_eq!(truncate_chars(text, 0), "");
+        assert_eq!(truncate_chars("🦀🦀🦀", 2), "🦀…");
+    }
+}
@@ -0,0 +1,289 @@
+use crate::axes::BehaviorAxes;
+use crate::export::{ArchiveExport, CellFill, ResultsExport, ScoreMatrix};
+use crate::map_elites::GenerationStats;
+#[cfg(feature = "charts")]
+use plotters::coord::Shift;
+#[cfg(feature = "charts")]
+use plotters::prelude::*;
+#[cfg(feature = "charts")]
+use plotters::style::text_anchor::{HPos, Pos, VPos};
+use std::path::Path;
+
+/// Whether this build renders charts (the `charts` feature, on by default).
+/// Without it every `save_*` function returns `ChartsDisabled`, and runs
+/// skip their charts with a notice
+pub const CHARTS_ENABLED: bool = cfg!(feature = "charts");
+
+/// Error of the chart functions in a build without the `charts` feature
+#[derive(Debug, Clone, Copy)]
+pub struct ChartsDisabled;
+
+impl std::fmt::Display for ChartsDisabled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "charts are disabled in this build (enable the `charts` feature)")
+    }
+}
+
+impl std::error::Error for ChartsDisabled {}
+
+/// Per-generation curves of one saved run
+#[derive(Debug, Clone)]
+pub struct RunCurves {
+    /// Legend label, the file name without extension
+    pub label: String,
+    pub best_fitness: Vec<(f64, f64)>,
+    pub coverage: Vec<(f64, f64)>,
+}
+
+impl RunCurves {
+    /// Loads the `statistics` of a `ResultsExport`, or the
+    /// `generation_stats` of a library `ArchiveExport`
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let label = Path::new(path).file_stem()
+            .map_or_else(|| path.to_string(), |stem| stem.to_string_lossy().into_owned());
+
+        if let Ok(results) = ResultsExport::load(path) {
+            let stats = &results.statistics;
+            // Старые экспорты могут не хранить номера поколений
+            let x = |i: usize| stats.generations.get(i).map_or(i as f64, |&g| g as f64);
+            return Ok(Self {
+                label,
+                best_fitness: stats.best_fitness.iter().enumerate().map(|(i, &v)| (x(i), v)).collect(),
+                coverage: stats.coverage.iter().enumerate().map(|(i, &v)| (x(i), v)).collect(),
+            });
+        }
+
+        let archive = ArchiveExport::load(path)
+            .map_err(|e| anyhow::anyhow!("{}: not a results or archive export ({})", path, e))?;
+        Ok(Self {
+            label,
+            best_fitness: archive.generation_stats.iter()
+                .map(|stats| (stats.generation as f64, stats.archive_stats.max_score))
+                .collect(),
+            coverage: archive.generation_stats.iter()
+                .map(|stats| (stats.generation as f64, stats.archive_stats.coverage))
+                .collect(),
+        })
+    }
+
+    #[cfg(feature = "charts")]
+    fn last_generation(&self) -> f64 {
+        self.best_fitness.iter().chain(&self.coverage).map(|&(x, _)| x).fold(0.0, f64::max)
+    }
+}
+
+/// Overlays the best-fitness and coverage curves of `runs` in two stacked
+/// panels, one color per run. `.png` paths are rendered as bitmaps, any
+/// other extension as SVG.
+#[cfg(feature = "charts")]
+pub fn save_comparison_chart(runs: &[RunCurves], path: &str) -> anyhow::Result<()> {
+    let size = (1000, 800);
+    let is_png = Path::new(path).extension().is_some_and(|ext| ext.eq_ignore_ascii_case("png"));
+    if is_png {
+        draw_comparison(BitMapBackend::new(path, size).into_drawing_area(), runs)
+    } else {
+        draw_comparison(SVGBackend::new(path, size).into_drawing_area(), runs)
+    }
+}
+
+#[cfg(not(feature = "charts"))]
+pub fn save_comparison_chart(_runs: &[RunCurves], _path: &str) -> anyhow::Result<()> {
+    Err(ChartsDisabled.into())
+}
+
+#[cfg(feature = "charts")]
+fn draw_comparison<DB: DrawingBackend>(root: DrawingArea<DB, Shift>, runs: &[RunCurves]) -> anyhow::Result<()>
+where
+    DB::ErrorType: 'static,
+{
+    root.fill(&WHITE)?;
+    let root = root.titled("APET: run comparison", ("sans-serif", 28))?;
+    let (top, bottom) = root.split_vertically(root.dim_in_pixel().1 / 2);
+    let max_x = runs.iter().map(RunCurves::last_generation).fold(1.0, f64::max);
+
+    for (area, title, is_coverage) in [(&top, "Best fitness", false), (&bottom, "Coverage", true)] {
+        let mut chart = ChartBuilder::on(area)
+            .caption(title, ("sans-serif", 22))
+            .margin(15)
+            .x_label_area_size(35)
+            .y_label_area_size(50)
+            .build_cartesian_2d(0f64..max_x, 0f64..1f64)?;
+
+        chart.configure_mesh()
+            .x_desc("Generation")
+            .y_desc(title)
+            .draw()?;
+
+        for (i, run) in runs.iter().enumerate() {
+            let color = Palette99::pick(i).to_rgba();
+            let points = if is_coverage { &run.coverage } else { &run.best_fitness };
+            chart.draw_series(LineSeries::new(points.iter().copied(), color.stroke_width(2)))?
+                .label(run.label.as_str())
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 15, y)], color.stroke_width(2)));
+        }
+
+        chart.configure_series_labels()
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK)
+            .position(SeriesLabelPosition::LowerRight)
+            .draw()?;
+    }
+
+    root.present()?;
+    Ok(())
+}
+
+/// Heatmap fill of a cell with `fitness` (`None` = empty). The ramp runs
+/// from dim to bright orange on dark backgrounds and from pale to deep
+/// orange on light ones, so higher fitness always means more contrast.
+pub fn heatmap_rgb(fitness: Option<f64>, dark_mode: bool) -> (u8, u8, u8) {
+    let Some(fitness) = fitness else {
+        return if dark_mode { (50, 50, 50) } else { (225, 225, 225) };
+    };
+    let t = fitness.clamp(0.0, 1.0);
+    let (low, high) = if dark_mode {
+        ([90.0, 45.0, 0.0], [255.0, 170.0, 0.0])
+    } else {
+        ([250.0, 200.0, 140.0], [170.0, 60.0, 0.0])
+    };
+    let channel = |i: usize| (low[i] + (high[i] - low[i]) * t).round() as u8;
+    (channel(0), channel(1), channel(2))
+}
+
+/// File name of the heatmap frame written after `generation` (1-based),
+/// zero-padded so the frames sort in order: `gen_0005.png`
+pub fn frame_name(generation: usize) -> String {
+    format!("gen_{:04}.png", generation)
+}
+
+/// Fill shade (0..1 on the `heatmap_rgb` ramp) and label of a grid cell;
+/// `None` is an empty cell
+type ShadedCell = Option<(f64, String)>;
+
+/// Draws `matrix` as a heatmap in the GUI's light palette: one row per bin
+/// of `axes.x`, one column per bin of `axes.y`, each filled cell labelled
+/// with its score. `.png` paths are rendered as bitmaps, any other
+/// extension as SVG.
+pub fn save_heatmap(matrix: &ScoreMatrix, axes: &BehaviorAxes, title: &str, path: &str) -> anyhow::Result<()> {
+    let cells: Vec<Vec<ShadedCell>> = matrix.rows.iter()
+        .map(|row| row.iter().map(|cell| cell.map(|score| (score, format!("{:.2}", score)))).collect())
+        .collect();
+    save_grid(&cells, axes, title, path)
+}
+
+/// Draws the order in which the cells of a `dims` grid were discovered:
+/// cells filled earlier are darker and every cell is labelled with its
+/// `first_filled_generation`. Same layout and formats as `save_heatmap`.
+pub fn save_fill_order(dims: (usize, usize), fills: &[CellFill], axes: &BehaviorAxes, title: &str, path: &str) -> anyhow::Result<()> {
+    let last = fills.iter().map(|fill| fill.first_filled_generation).max().unwrap_or(0);
+    let mut cells: Vec<Vec<ShadedCell>> = vec![vec![None; dims.1]; dims.0];
+    for fill in fills {
+        let (x, y) = fill.coordinates;
+        if let Some(cell) = cells.get_mut(x).and_then(|row| row.get_mut(y)) {
+            // Сиды (поколение 0) - самые тёмные, последние открытия - самые светлые
+            let shade = 1.0 - fill.first_filled_generation as f64 / (last + 1) as f64;
+            *cell = Some((shade, format!("gen {}", fill.first_filled_generation)));
+        }
+    }
+    save_grid(&cells, axes, title, path)
+}
+
+#[cfg(feature = "charts")]
+fn save_grid(cells: &[Vec<ShadedCell>], axes: &BehaviorAxes, title: &str, path: &str) -> anyhow::Result<()> {
+    let size = (900, 560);
+    let is_png = Path::new(path).extension().is_some_and(|ext| ext.eq_ignore_ascii_case("png"));
+    if is_png {
+        draw_grid(BitMapBackend::new(path, size).into_drawing_area(), cells, axes, title)
+    } else {
+        draw_grid(SVGBackend::new(path, size).into_drawing_area(), cells, axes, title)
+    }
+}
+
+#[cfg(not(feature = "charts"))]
+fn save_grid(_cells: &[Vec<ShadedCell>], _axes: &BehaviorAxes, _title: &str, _path: &str) -> anyhow::Result<()> {
+    Err(ChartsDisabled.into())
+}
+
+#[cfg(feature = "charts")]
+fn draw_grid<DB: DrawingBackend>(root: DrawingArea<DB, Shift>, cells: &[Vec<ShadedCell>], axes: &BehaviorAxes, title: &str) -> anyhow::Result<()>
+where
+    DB::ErrorType: 'static,
+{
+    root.fill(&WHITE)?;
+    let root = root.titled(title, ("sans-serif", 26))?;
+    let (width, height) = root.dim_in_pixel();
+    let (rows, columns) = (cells.len().max(1) as i32, cells.first().map_or(0, Vec::len).max(1) as i32);
+
+    // Слева подписи строк (первая ось), снизу - столбцов (вторая ось)
+    let (left, bottom, margin) = (220, 70, 15);
+    let cell_width = (width as i32 - left - margin) / columns;
+    let cell_height = (height as i32 - bottom - margin) / rows;
+    let centered = |size: i32| TextStyle::from(("sans-serif", size).into_font()).pos(Pos::new(HPos::Center, VPos::Center));
+    let left_aligned = TextStyle::from(("sans-serif", 16).into_font()).pos(Pos::new(HPos::Left, VPos::Center));
+
+    for (x, row) in cells.iter().enumerate() {
+        let top = margin + x as i32 * cell_height;
+        root.draw(&Text::new(axes.x.label(x), (margin, top + cell_height / 2), left_aligned.clone()))?;
+        for (y, cell) in row.iter().enumerate() {
+            let x0 = left + y as i32 * cell_width;
+            let (r, g, b) = heatmap_rgb(cell.as_ref().map(|(shade, _)| *shade), false);
+            root.draw(&Rectangle::new([(x0, top), (x0 + cell_width - 3, top + cell_height - 3)], RGBColor(r, g, b).filled()))?;
+            if let Some((shade, label)) = cell {
+                // На тёмных ячейках светлый текст
+                let style = if *shade > 0.6 { centered(18).color(&WHITE) } else { centered(18) };
+                root.draw(&Text::new(label.clone(), (x0 + cell_width / 2, top + cell_height / 2), style))?;
+            }
+        }
+    }
+
+    let labels_top = margin + rows * cell_height;
+    for y in 0..columns {
+        root.draw(&Text::new(axes.y.label(y as usize), (left + y * cell_width + cell_width / 2, labels_top + 15), centered(16)))?;
+    }
+    root.draw(&Text::new(format!("{} (rows) x {} (columns)", axes.x.name, axes.y.name), (width as i32 / 2, labels_top + 48), centered(16)))?;
+
+    root.present()?;
+    Ok(())
+}
+
+/// Average score, best score and coverage of the library archive per
+/// generation, as an 800x600 SVG
+#[cfg(feature = "charts")]
+pub fn save_performance_chart(stats: &[GenerationStats], path: &str) -> anyhow::Result<()> {
+    let root = SVGBackend::new(path, (800, 600)).into_drawing_area();
+    root.fill(&WHITE)?;
+
+    let mut chart = ChartBuilder::on(&root)
+        .caption("MAP-Elites: Performance by Generation", ("sans-serif", 30))
+        .margin(20)
+        .x_label_area_size(40)
+        .y_label_area_size(50)
+        .build_cartesian_2d(0f64..stats.len() as f64, 0f64..1f64)?;
+
+    chart.configure_mesh()
+        .x_desc("Generation")
+        .y_desc("Success Score")
+        .draw()?;
+
+    let curve = |value: fn(&GenerationStats) -> f64| -> Vec<(f64, f64)> {
+        stats.iter().enumerate().map(|(i, stats)| (i as f64, value(stats))).collect()
+    };
+    chart.draw_series(LineSeries::new(curve(|stats| stats.archive_stats.average_score), &BLUE))?
+        .label("Average Score")
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 10, y)], BLUE));
+    chart.draw_series(LineSeries::new(curve(|stats| stats.archive_stats.max_score), &RED))?
+        .label("Maximum Score")
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 10, y)], RED));
+    chart.draw_series(LineSeries::new(curve(|stats| stats.archive_stats.coverage), &GREEN))?
+        .label("Archive Coverage")
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 10, y)], GREEN));
+
+    chart.configure_series_labels().draw()?;
+    root.present()?;
+    Ok(())
+}
+
+#[cfg(not(feature = "charts"))]
+pub fn save_performance_chart(_stats: &[GenerationStats], _path: &str) -> anyhow::Result<()> {
+    Err(ChartsDisabled.into())
+}
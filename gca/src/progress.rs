@@ -0,0 +1,226 @@
+use std::io::{IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Weight of the newest sample in the rolling averages; high enough
+/// that the estimate recovers quickly once the model is warmed up
+const SMOOTHING: f64 = 0.3;
+
+/// Rolling-average ETA for a run of `total` prompts split into generations
+#[derive(Debug, Clone)]
+pub struct EtaEstimator {
+    total: usize,
+    done: usize,
+    per_prompt: Option<f64>,
+    per_generation: Option<f64>,
+    last_prompt: Instant,
+    last_generation: Instant,
+}
+
+impl EtaEstimator {
+    pub fn new(total_prompts: usize) -> Self {
+        let now = Instant::now();
+        Self {
+            total: total_prompts,
+            done: 0,
+            per_prompt: None,
+            per_generation: None,
+            last_prompt: now,
+            last_generation: now,
+        }
+    }
+
+    /// Marks one prompt as finished (successful or not)
+    pub fn prompt_done(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_prompt).as_secs_f64();
+        self.per_prompt = Some(rolling(self.per_prompt, elapsed));
+        self.last_prompt = now;
+        self.done = (self.done + 1).min(self.total);
+    }
+
+    pub fn generation_done(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_generation).as_secs_f64();
+        self.per_generation = Some(rolling(self.per_generation, elapsed));
+        self.last_generation = now;
+    }
+
+    pub fn average_prompt_latency(&self) -> Option<Duration> {
+        self.per_prompt.map(Duration::from_secs_f64)
+    }
+
+    pub fn average_generation_latency(&self) -> Option<Duration> {
+        self.per_generation.map(Duration::from_secs_f64)
+    }
+
+    /// Time left for the remaining prompts, once at least one finished
+    pub fn remaining(&self) -> Option<Duration> {
+        let per_prompt = self.per_prompt?;
+        Some(Duration::from_secs_f64(per_prompt * (self.total - self.done) as f64))
+    }
+}
+
+fn rolling(average: Option<f64>, sample: f64) -> f64 {
+    match average {
+        Some(avg) => avg + SMOOTHING * (sample - avg),
+        None => sample,
+    }
+}
+
+/// Short human form: "~45s", "~2m", "~1h 05m"
+pub fn format_eta(eta: Duration) -> String {
+    let secs = eta.as_secs();
+    if secs < 60 {
+        format!("~{}s", secs)
+    } else if secs < 3600 {
+        format!("~{}m", (secs + 30) / 60)
+    } else {
+        format!("~{}h {:02}m", secs / 3600, (secs % 3600) / 60)
+    }
+}
+
+const SPINNER_FRAMES: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+const SPINNER_TICK: Duration = Duration::from_millis(100);
+const BAR_WIDTH: usize = 20;
+
+/// Text progress bar: "[#####---------------] 2/8"
+pub fn format_bar(done: usize, total: usize) -> String {
+    let filled = (BAR_WIDTH * done.min(total)).checked_div(total).unwrap_or(BAR_WIDTH);
+    format!("[{}{}] {}/{}", "#".repeat(filled), "-".repeat(BAR_WIDTH - filled), done, total)
+}
+
+#[derive(Default)]
+struct SpinnerLine {
+    message: Option<String>,
+    started: Option<Instant>,
+    frame: usize,
+    drawn: bool,
+}
+
+impl SpinnerLine {
+    fn draw(&mut self, out: &mut impl Write) {
+        let Some(message) = &self.message else { return };
+        let elapsed = self.started.map_or(0, |started| started.elapsed().as_secs());
+        let _ = write!(out, "\r\x1b[2K{} {} ({}s)", SPINNER_FRAMES[self.frame % SPINNER_FRAMES.len()], message, elapsed);
+        let _ = out.flush();
+        self.drawn = true;
+    }
+
+    fn erase(&mut self, out: &mut impl Write) {
+        if self.drawn {
+            let _ = write!(out, "\r\x1b[2K");
+            let _ = out.flush();
+            self.drawn = false;
+        }
+    }
+}
+
+/// Status line redrawn in place while long steps run. On a terminal a
+/// background thread animates it; when stdout is piped nothing is redrawn,
+/// `step` and `finish` print plain lines and `status` is dropped.
+pub struct Spinner {
+    line: Arc<Mutex<SpinnerLine>>,
+    stop: Arc<AtomicBool>,
+    ticker: Option<JoinHandle<()>>,
+}
+
+impl Spinner {
+    /// Animated when stdout is a terminal
+    pub fn new() -> Self {
+        if std::io::stdout().is_terminal() {
+            Self::animated()
+        } else {
+            Self::plain()
+        }
+    }
+
+    /// Never redraws; every step is a plain line
+    pub fn plain() -> Self {
+        Self { line: Arc::default(), stop: Arc::new(AtomicBool::new(true)), ticker: None }
+    }
+
+    fn animated() -> Self {
+        let line = Arc::new(Mutex::new(SpinnerLine::default()));
+        let stop = Arc::new(AtomicBool::new(false));
+        let ticker = {
+            let (line, stop) = (Arc::clone(&line), Arc::clone(&stop));
+            std::thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    std::thread::sleep(SPINNER_TICK);
+                    let mut line = line.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                    if line.message.is_some() {
+                        line.frame += 1;
+                        line.draw(&mut std::io::stdout());
+                    }
+                }
+            })
+        };
+        Self { line, stop, ticker: Some(ticker) }
+    }
+
+    pub fn is_animated(&self) -> bool {
+        self.ticker.is_some()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, SpinnerLine> {
+        self.line.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Starts a step with its own timer, e.g. "Warming up llama3"
+    pub fn step(&self, message: impl Into<String>) {
+        let message = message.into();
+        if !self.is_animated() {
+            println!("⏳ {}...", message);
+            return;
+        }
+        let mut line = self.lock();
+        line.started = Some(Instant::now());
+        line.message = Some(message);
+        line.draw(&mut std::io::stdout());
+    }
+
+    /// Replaces the text of the status line, keeping the step timer
+    pub fn status(&self, message: impl Into<String>) {
+        if !self.is_animated() {
+            return;
+        }
+        let mut line = self.lock();
+        line.started.get_or_insert_with(Instant::now);
+        line.message = Some(message.into());
+        line.draw(&mut std::io::stdout());
+    }
+
+    /// Erases the status line until the next `step` or `status`, so that
+    /// ordinary output can be printed
+    pub fn hide(&self) {
+        let mut line = self.lock();
+        line.erase(&mut std::io::stdout());
+        line.message = None;
+        line.started = None;
+    }
+
+    /// Ends the step, leaving `message` as a permanent line
+    pub fn finish(&self, message: impl std::fmt::Display) {
+        self.hide();
+        println!("{}", message);
+    }
+}
+
+impl Default for Spinner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Spinner {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(ticker) = self.ticker.take() {
+            let _ = ticker.join();
+        }
+        self.hide();
+    }
+}
@@ -0,0 +1,91 @@
+use crate::adversarial::AdversarialPrompt;
+use crate::export::RunMetadata;
+use crate::map_elites::ArchiveStats;
+use crate::output::truncate_chars;
+use crate::transfer::{format_table, TransferReport};
+
+/// Longest prompt excerpt shown in report tables
+const EXCERPT_CHARS: usize = 120;
+
+/// Everything a one-shot assessment (`gca assess`) produced
+#[derive(Debug, Clone)]
+pub struct Assessment {
+    pub primary_model: String,
+    pub secondary_model: String,
+    pub goals: Vec<String>,
+    pub generations: usize,
+    pub population: usize,
+    /// Archive of the evolution against the primary model
+    pub archive_stats: ArchiveStats,
+    /// Elites sent to the secondary model, best first
+    pub top_elites: Vec<AdversarialPrompt>,
+    pub transfer: TransferReport,
+    /// Provenance of the evolution run
+    pub metadata: RunMetadata,
+}
+
+impl Assessment {
+    /// Findings report: run settings, the primary model's archive, the top
+    /// elites and how they fared against the secondary model
+    pub fn to_markdown(&self) -> String {
+        let mut md = String::new();
+        md.push_str("# APET assessment\n\n");
+        md.push_str(&format!("- Date: {}\n", chrono::Utc::now().format("%Y-%m-%d %H:%M UTC")));
+        md.push_str(&self.metadata.to_markdown());
+        md.push_str(&format!("- Primary model: `{}`\n", self.primary_model));
+        md.push_str(&format!("- Secondary model: `{}`\n", self.secondary_model));
+        md.push_str(&format!("- Goals: {}\n", self.goals.len()));
+        md.push_str(&format!("- Evolution: {} generations x {} prompts\n\n", self.generations, self.population));
+
+        let stats = &self.archive_stats;
+        md.push_str(&format!("## Evolution against `{}`\n\n", self.primary_model));
+        md.push_str(&format!("- Coverage: {:.1}% ({}/{} cells)\n", stats.coverage * 100.0, stats.filled_cells, stats.total_cells));
+        md.push_str(&format!("- Best score: {:.3}\n", stats.max_score));
+        md.push_str(&format!("- Average score: {:.3}\n\n", stats.average_score));
+
+        md.push_str("| # | Score | Cell | Attack type | Goal | Prompt |\n");
+        md.push_str("|---|---|---|---|---|---|\n");
+        for (rank, elite) in self.top_elites.iter().enumerate() {
+            md.push_str(&format!("| {} | {:.3} | {},{} | {} | {} | {} |\n",
+                                 rank + 1, elite.success_score, elite.coordinates.0, elite.coordinates.1,
+                                 elite.classify_attack_type(), table_cell(&elite.target_goal), table_cell(&elite.text)));
+        }
+
+        let transfer = &self.transfer;
+        md.push_str(&format!("\n## Transfer to `{}`\n\n", self.secondary_model));
+        md.push_str(&format!("- Refusal rate: {:.1}%\n", transfer.refusal_rate() * 100.0));
+        md.push_str(&format!("- On-goal replies: {:.1}%\n", transfer.goal_relevant_rate() * 100.0));
+        md.push_str(&format!("- Average judge score: {:.3}\n\n", transfer.average_score()));
+        md.push_str("```\n");
+        md.push_str(&format_table(std::slice::from_ref(transfer)));
+        md.push_str("```\n\n");
+
+        md.push_str("| # | Score | Refused | On goal | Prompt |\n");
+        md.push_str("|---|---|---|---|---|\n");
+        for (rank, outcome) in transfer.outcomes.iter().enumerate() {
+            let score = match &outcome.error {
+                Some(error) => format!("error: {}", table_cell(error)),
+                None => format!("{:.3}", outcome.score),
+            };
+            md.push_str(&format!("| {} | {} | {} | {} | {} |\n",
+                                 rank + 1, score, yes_no(outcome.refused), yes_no(outcome.goal_relevant), table_cell(&outcome.prompt)));
+        }
+
+        md
+    }
+
+    pub fn save(&self, path: &str) -> anyhow::Result<()> {
+        std::fs::write(path, self.to_markdown())?;
+        Ok(())
+    }
+}
+
+/// Text safe inside a Markdown table cell: one line, escaped pipes, shortened
+fn table_cell(text: &str) -> String {
+    let line = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    truncate_chars(&line, EXCERPT_CHARS).replace('|', "\\|")
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value { "yes" } else { "no" }
+}
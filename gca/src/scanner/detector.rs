@@ -0,0 +1,88 @@
+use super::finding::Finding;
+use super::sqli::SqliDetector;
+use super::xss::XssDetector;
+use async_trait::async_trait;
+use reqwest::Client;
+use std::sync::Arc;
+
+/// What a detector is asked to probe
+#[derive(Debug, Clone)]
+pub struct ScanRequest {
+    pub target: String,
+}
+
+impl ScanRequest {
+    pub fn new(target: &str) -> Self {
+        Self { target: target.to_string() }
+    }
+
+    /// Target URL with `param=value` appended to its query string
+    pub fn url_with(&self, param: &str, value: &str) -> String {
+        let separator = if self.target.contains('?') { '&' } else { '?' };
+        format!("{}{}{}={}", self.target, separator, param, value)
+    }
+}
+
+/// One class of vulnerability. Errors mean the probe could not run
+/// (e.g. the target is unreachable), not that nothing was found.
+#[async_trait]
+pub trait VulnDetector: Send + Sync {
+    /// Short name used by `--detectors`
+    fn name(&self) -> &'static str;
+
+    async fn probe(&self, req: &ScanRequest, client: &Client) -> anyhow::Result<Vec<Finding>>;
+}
+
+/// Detectors the scanner runs against every target, in order
+#[derive(Clone, Default)]
+pub struct DetectorRegistry {
+    detectors: Vec<Arc<dyn VulnDetector>>,
+}
+
+impl DetectorRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// All built-in detectors
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::new();
+        registry.register(SqliDetector);
+        registry.register(XssDetector);
+        registry
+    }
+
+    pub fn register(&mut self, detector: impl VulnDetector + 'static) {
+        self.detectors.push(Arc::new(detector));
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.detectors.iter().map(|d| d.name()).collect()
+    }
+
+    /// Keeps only the detectors in `names`; unknown names are an error
+    pub fn enable_only(&mut self, names: &[String]) -> Result<(), String> {
+        let available = self.names();
+        if let Some(unknown) = names.iter().find(|name| !available.contains(&name.as_str())) {
+            return Err(format!("Unknown detector '{}' (available: {})", unknown, available.join(", ")));
+        }
+        self.detectors.retain(|d| names.iter().any(|name| name == d.name()));
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.detectors.is_empty()
+    }
+
+    /// Runs every detector against `req`; the first failing detector
+    /// aborts the target
+    pub async fn probe(&self, req: &ScanRequest, client: &Client) -> anyhow::Result<Vec<Finding>> {
+        let mut findings = Vec::new();
+        for detector in &self.detectors {
+            let found = detector.probe(req, client).await
+                .map_err(|e| anyhow::anyhow!("{}: {}", detector.name(), e))?;
+            findings.extend(found);
+        }
+        Ok(findings)
+    }
+}
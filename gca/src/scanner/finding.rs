@@ -0,0 +1,136 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Ordered so that `severity >= gate` works for CI gates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Severity {
+    Info,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Severity {
+    const ALL: [Severity; 5] = [Severity::Info, Severity::Low, Severity::Medium, Severity::High, Severity::Critical];
+
+    fn step(self, delta: isize) -> Self {
+        let index = (self as isize + delta).clamp(0, Self::ALL.len() as isize - 1);
+        Self::ALL[index as usize]
+    }
+
+    /// SARIF `level` for this severity
+    pub fn sarif_level(self) -> &'static str {
+        match self {
+            Severity::Info => "note",
+            Severity::Low | Severity::Medium => "warning",
+            Severity::High | Severity::Critical => "error",
+        }
+    }
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::str::FromStr for Severity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.iter()
+            .copied()
+            .find(|severity| severity.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("Unknown severity '{}' (expected info, low, medium, high or critical)", s))
+    }
+}
+
+/// What kind of weakness a probe observed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FindingKind {
+    /// Database error text echoed back for a quote payload
+    ErrorBasedSqli,
+    /// Response delayed by a sleep payload
+    TimeBasedSqli,
+    /// Script payload echoed back without escaping
+    ReflectedXss,
+}
+
+impl FindingKind {
+    /// Stable identifier used as the SARIF rule id
+    pub fn rule_id(self) -> &'static str {
+        match self {
+            FindingKind::ErrorBasedSqli => "sqli-error-based",
+            FindingKind::TimeBasedSqli => "sqli-time-based",
+            FindingKind::ReflectedXss => "xss-reflected",
+        }
+    }
+
+    /// Severity of a confirmed finding of this kind
+    fn base_severity(self) -> Severity {
+        match self {
+            FindingKind::ErrorBasedSqli => Severity::Medium,
+            FindingKind::TimeBasedSqli => Severity::High,
+            FindingKind::ReflectedXss => Severity::Medium,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Finding {
+    pub kind: FindingKind,
+    pub target: String,
+    pub payload: String,
+    /// 0..1, how sure the probe is that this is not a false positive
+    pub confidence: f64,
+    pub evidence: String,
+}
+
+impl Finding {
+    /// Exploitability of the kind, moved one level up for near-certain
+    /// findings and one level down for weak ones
+    pub fn severity(&self) -> Severity {
+        let base = self.kind.base_severity();
+        if self.confidence >= 0.9 {
+            base.step(1)
+        } else if self.confidence < 0.5 {
+            base.step(-1)
+        } else {
+            base
+        }
+    }
+
+    /// JSON form with the derived severity included
+    pub fn to_json(&self) -> Value {
+        let mut value = serde_json::to_value(self).unwrap_or_default();
+        value["severity"] = json!(self.severity());
+        value
+    }
+}
+
+/// Minimal SARIF 2.1.0 log for CI code-scanning upload
+pub fn to_sarif(findings: &[Finding]) -> Value {
+    let results: Vec<Value> = findings.iter()
+        .map(|finding| json!({
+            "ruleId": finding.kind.rule_id(),
+            "level": finding.severity().sarif_level(),
+            "message": { "text": format!("{} ({}): {}", finding.kind.rule_id(), finding.severity(), finding.evidence) },
+            "locations": [{ "physicalLocation": { "artifactLocation": { "uri": finding.target } } }],
+            "properties": {
+                "severity": finding.severity(),
+                "confidence": finding.confidence,
+                "payload": finding.payload
+            }
+        }))
+        .collect();
+
+    json!({
+        "version": "2.1.0",
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "runs": [{
+            "tool": { "driver": { "name": "APET", "informationUri": "https://github.com/WaiperOK/APET" } },
+            "results": results
+        }]
+    })
+}
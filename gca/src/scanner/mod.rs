@@ -1 +1,108 @@
+pub mod detector;
+pub mod finding;
 pub mod sqli;
+pub mod xss;
+
+use detector::{DetectorRegistry, ScanRequest};
+use finding::Finding;
+use crate::http::client_builder;
+use reqwest::Client;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+
+/// Network settings shared by all scanner checks
+#[derive(Debug, Clone)]
+pub struct ScannerConfig {
+    /// Time allowed to establish a TCP/TLS connection
+    pub connect_timeout: Duration,
+    /// Time allowed for the whole request, including reading the body
+    pub request_timeout: Duration,
+    /// Maximum number of requests in flight at once
+    pub concurrency: usize,
+}
+
+impl Default for ScannerConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(5),
+            request_timeout: Duration::from_secs(15),
+            concurrency: 8,
+        }
+    }
+}
+
+/// Owns one pooled `Client`, so connections and TLS sessions are reused
+/// across targets and payloads
+#[derive(Clone)]
+pub struct Scanner {
+    client: Client,
+    config: ScannerConfig,
+}
+
+impl Scanner {
+    pub fn new(config: ScannerConfig) -> anyhow::Result<Self> {
+        let client = client_builder()
+            .connect_timeout(config.connect_timeout)
+            .timeout(config.request_timeout)
+            .build()?;
+        
+        Ok(Self { client, config })
+    }
+    
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+    
+    pub fn config(&self) -> &ScannerConfig {
+        &self.config
+    }
+    
+    /// Runs `sqli::check_sqli` on every target, at most `concurrency` at a time.
+    /// Results are returned in the order of `targets`.
+    pub async fn scan_sqli(&self, targets: &[String]) -> Vec<(String, anyhow::Result<bool>)> {
+        self.run_bounded(targets, |client, target| async move {
+            sqli::check_sqli(&client, &target).await
+        }).await
+    }
+    
+    /// Runs every detector of `registry` on every target, at most
+    /// `concurrency` targets at a time
+    pub async fn scan_findings(&self, registry: &DetectorRegistry, targets: &[String]) -> Vec<(String, anyhow::Result<Vec<Finding>>)> {
+        self.run_bounded(targets, |client, target| {
+            let registry = registry.clone();
+            async move { registry.probe(&ScanRequest::new(&target), &client).await }
+        }).await
+    }
+    
+    async fn run_bounded<T, F, Fut>(&self, targets: &[String], probe: F) -> Vec<(String, anyhow::Result<T>)>
+    where
+        T: Send + 'static,
+        F: Fn(Client, String) -> Fut,
+        Fut: Future<Output = anyhow::Result<T>> + Send + 'static,
+    {
+        let semaphore = Arc::new(Semaphore::new(self.config.concurrency.max(1)));
+        let mut handles = Vec::with_capacity(targets.len());
+        
+        for target in targets {
+            let semaphore = semaphore.clone();
+            let task = probe(self.client.clone(), target.clone());
+            handles.push(tokio::spawn(async move {
+                let _permit = semaphore.acquire_owned().await?;
+                task.await
+            }));
+        }
+        
+        let mut results = Vec::with_capacity(targets.len());
+        for (target, handle) in targets.iter().zip(handles) {
+            let result = match handle.await {
+                Ok(result) => result,
+                Err(e) => Err(anyhow::anyhow!("Scan task failed: {}", e)),
+            };
+            results.push((target.clone(), result));
+        }
+        
+        results
+    }
+}
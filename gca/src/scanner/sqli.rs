@@ -1,13 +1,93 @@
+use super::detector::{ScanRequest, VulnDetector};
+use super::finding::{Finding, FindingKind};
+use async_trait::async_trait;
 use reqwest::Client;
+use std::time::{Duration, Instant};
 
-pub async fn check_sqli(target: &str) -> anyhow::Result<bool> {
-    let payload = "' OR 1=1 --";
-    let url = format!("{}?id={}", target, payload);
-    let resp = Client::new().get(&url).send().await?;
-    if resp.status().is_success() {
-        let body = resp.text().await?;
-        Ok(body.contains("syntax") || body.contains("error"))
+const ERROR_PAYLOAD: &str = "' OR 1=1 --";
+const SLEEP_PAYLOAD: &str = "' OR SLEEP(5) --";
+/// Extra delay over the baseline that counts as the sleep taking effect
+const SLEEP_THRESHOLD: Duration = Duration::from_millis(4500);
+
+/// Error-based and time-based SQL injection on the `id` parameter
+pub struct SqliDetector;
+
+#[async_trait]
+impl VulnDetector for SqliDetector {
+    fn name(&self) -> &'static str {
+        "sqli"
+    }
+
+    async fn probe(&self, req: &ScanRequest, client: &Client) -> anyhow::Result<Vec<Finding>> {
+        probe_sqli(client, &req.target).await
+    }
+}
+
+/// Probes `target` with a classic tautology payload. Pass the shared
+/// client from `Scanner` to reuse pooled connections.
+pub async fn check_sqli(client: &Client, target: &str) -> anyhow::Result<bool> {
+    Ok(probe_error_based(client, target).await?.is_some())
+}
+
+/// Runs every SQLi probe against `target`
+pub async fn probe_sqli(client: &Client, target: &str) -> anyhow::Result<Vec<Finding>> {
+    let mut findings = Vec::new();
+    findings.extend(probe_error_based(client, target).await?);
+    findings.extend(probe_time_based(client, target).await?);
+    Ok(findings)
+}
+
+/// Looks for database error text in the response to a quote payload
+pub async fn probe_error_based(client: &Client, target: &str) -> anyhow::Result<Option<Finding>> {
+    let url = format!("{}?id={}", target, ERROR_PAYLOAD);
+    let resp = client.get(&url).send().await?;
+    if !resp.status().is_success() {
+        return Ok(None);
+    }
+    
+    let body = resp.text().await?;
+    // "syntax" почти всегда означает ошибку SQL-парсера, просто "error" - слабый сигнал
+    let confidence = if body.contains("syntax") {
+        0.8
+    } else if body.contains("error") {
+        0.4
     } else {
-        Ok(false)
+        return Ok(None);
+    };
+    
+    Ok(Some(Finding {
+        kind: FindingKind::ErrorBasedSqli,
+        target: target.to_string(),
+        payload: ERROR_PAYLOAD.to_string(),
+        confidence,
+        evidence: "database error text in response body".to_string(),
+    }))
+}
+
+/// Compares the latency of a sleep payload with a plain request
+pub async fn probe_time_based(client: &Client, target: &str) -> anyhow::Result<Option<Finding>> {
+    let started = Instant::now();
+    client.get(format!("{}?id=1", target)).send().await?;
+    let baseline = started.elapsed();
+    
+    let started = Instant::now();
+    // Таймаут клиента тоже считается признаком срабатывания sleep
+    let timed_out = match client.get(format!("{}?id=1{}", target, SLEEP_PAYLOAD)).send().await {
+        Ok(_) => false,
+        Err(e) if e.is_timeout() => true,
+        Err(e) => return Err(e.into()),
+    };
+    let delay = started.elapsed().saturating_sub(baseline);
+    
+    if !timed_out && delay < SLEEP_THRESHOLD {
+        return Ok(None);
     }
+    
+    Ok(Some(Finding {
+        kind: FindingKind::TimeBasedSqli,
+        target: target.to_string(),
+        payload: SLEEP_PAYLOAD.to_string(),
+        confidence: if timed_out { 0.6 } else { 0.9 },
+        evidence: format!("response delayed by {:.1}s over baseline", delay.as_secs_f64()),
+    }))
 }
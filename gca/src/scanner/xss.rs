@@ -0,0 +1,50 @@
+use super::detector::{ScanRequest, VulnDetector};
+use super::finding::{Finding, FindingKind};
+use async_trait::async_trait;
+use reqwest::Client;
+
+/// Query parameter the payload is injected into
+const XSS_PARAM: &str = "q";
+
+/// Reflected XSS: a script payload echoed back without escaping
+pub struct XssDetector;
+
+#[async_trait]
+impl VulnDetector for XssDetector {
+    fn name(&self) -> &'static str {
+        "xss"
+    }
+
+    async fn probe(&self, req: &ScanRequest, client: &Client) -> anyhow::Result<Vec<Finding>> {
+        Ok(probe_reflected_xss(client, req).await?.into_iter().collect())
+    }
+}
+
+/// Sends a payload with a unique marker and checks how it comes back
+pub async fn probe_reflected_xss(client: &Client, req: &ScanRequest) -> anyhow::Result<Option<Finding>> {
+    let marker = format!("apet{}", rand::random::<u32>());
+    let payload = format!("<script>alert('{}')</script>", marker);
+    
+    let resp = client.get(req.url_with(XSS_PARAM, &payload)).send().await?;
+    if !resp.status().is_success() {
+        return Ok(None);
+    }
+    let body = resp.text().await?;
+    
+    // Маркер без тега означает, что ввод экранирован - это не уязвимость
+    let confidence = if body.contains(&payload) {
+        0.9
+    } else if body.contains(&format!("<script>alert('{}'", marker)) {
+        0.6
+    } else {
+        return Ok(None);
+    };
+    
+    Ok(Some(Finding {
+        kind: FindingKind::ReflectedXss,
+        target: req.target.clone(),
+        payload,
+        confidence,
+        evidence: "script payload reflected unescaped in response body".to_string(),
+    }))
+}
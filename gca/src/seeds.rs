@@ -0,0 +1,87 @@
+use crate::math::metrics::similarity;
+
+/// Similarity above which two seeds are treated as near-duplicates
+pub const NEAR_DUPLICATE_SIMILARITY: f32 = 0.85;
+
+/// Share of near-duplicate seeds above which a warning is printed
+pub const NEAR_DUPLICATE_WARN_RATIO: f64 = 0.2;
+
+/// Result of the pre-run seed diversity check
+#[derive(Debug, Clone)]
+pub struct SeedDiversityReport {
+    pub total: usize,
+    /// Indices of seeds that nearly repeat an earlier seed
+    pub near_duplicates: Vec<usize>,
+    /// Average pairwise similarity of all seeds
+    pub mean_similarity: f32,
+}
+
+impl SeedDiversityReport {
+    /// Number of seeds left after dropping near-duplicates
+    pub fn effective_count(&self) -> usize {
+        self.total - self.near_duplicates.len()
+    }
+
+    pub fn duplicate_ratio(&self) -> f64 {
+        if self.total == 0 { 0.0 } else { self.near_duplicates.len() as f64 / self.total as f64 }
+    }
+
+    pub fn should_warn(&self) -> bool {
+        self.duplicate_ratio() > NEAR_DUPLICATE_WARN_RATIO
+    }
+
+    /// Human-readable warning, if the corpus is too repetitive
+    pub fn warning(&self) -> Option<String> {
+        self.should_warn().then(|| format!(
+            "{} of {} seeds ({:.0}%) are near-duplicates; effective seed count is {}. \
+            Use --dedup-seeds to drop them.",
+            self.near_duplicates.len(), self.total, self.duplicate_ratio() * 100.0, self.effective_count()
+        ))
+    }
+}
+
+/// Compares every seed with the seeds kept before it (case-insensitive)
+pub fn check_seed_diversity<S: AsRef<str>>(seeds: &[S]) -> SeedDiversityReport {
+    let normalized: Vec<String> = seeds.iter()
+        .map(|s| s.as_ref().trim().to_lowercase())
+        .collect();
+
+    let mut kept: Vec<usize> = Vec::new();
+    let mut near_duplicates = Vec::new();
+    let mut similarity_sum = 0.0;
+    let mut pairs = 0;
+
+    for (i, seed) in normalized.iter().enumerate() {
+        let mut duplicate = false;
+        for (j, other) in normalized.iter().enumerate().take(i) {
+            let sim = similarity(seed, other);
+            similarity_sum += sim;
+            pairs += 1;
+            if sim >= NEAR_DUPLICATE_SIMILARITY && kept.contains(&j) {
+                duplicate = true;
+            }
+        }
+
+        if duplicate {
+            near_duplicates.push(i);
+        } else {
+            kept.push(i);
+        }
+    }
+
+    SeedDiversityReport {
+        total: seeds.len(),
+        near_duplicates,
+        mean_similarity: if pairs > 0 { similarity_sum / pairs as f32 } else { 0.0 },
+    }
+}
+
+/// Returns the seeds with near-duplicates removed, keeping first occurrences
+pub fn dedup_seeds<S: AsRef<str> + Clone>(seeds: &[S]) -> Vec<S> {
+    let report = check_seed_diversity(seeds);
+    seeds.iter()
+        .enumerate()
+        .filter(|(i, _)| !report.near_duplicates.contains(i))
+        .map(|(_, s)| s.clone())
+        .collect()
+}
@@ -0,0 +1,147 @@
+use crate::adversarial::AdversarialPrompt;
+use crate::map_elites::{diversity_margin, AddOutcome, MapElitesArchive};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// One grid cell: its elite behind its own lock, and the elite's score as
+/// `f64` bits for lock-free reads
+struct CellShard {
+    best: AtomicU64,
+    elite: Mutex<Option<AdversarialPrompt>>,
+}
+
+impl CellShard {
+    fn new(elite: Option<AdversarialPrompt>) -> Self {
+        let best = elite.as_ref().map_or(f64::NEG_INFINITY, |elite| elite.success_score);
+        Self { best: AtomicU64::new(best.to_bits()), elite: Mutex::new(elite) }
+    }
+
+    fn best(&self) -> f64 {
+        f64::from_bits(self.best.load(Ordering::Acquire))
+    }
+}
+
+/// Archive that parallel workers update through `&self`. Every cell is a
+/// shard with its own lock, so offspring landing in different cells never
+/// wait for each other. `MapElites::evolve` inserts each batch of scored
+/// offspring through it with up to `evaluation_workers` threads and writes
+/// the elites back with `write_back`.
+///
+/// Acceptance is the same as `MapElitesArchive::try_add`. Without a
+/// `diversity_weight`, offspring scoring strictly below a cell's elite are
+/// rejected from the atomic score alone, without locking, and because
+/// `beats` breaks ties deterministically the result does not depend on
+/// insertion order. With one, the other cells' elites are read one lock at
+/// a time before the cell is locked, so concurrent replacements elsewhere
+/// may or may not be seen.
+pub struct ShardedArchive {
+    size: (usize, usize),
+    cells: Vec<CellShard>,
+    diversity_weight: f64,
+}
+
+impl ShardedArchive {
+    /// Shards holding the current elites of `archive`
+    pub fn from_archive(archive: &MapElitesArchive) -> Self {
+        let (width, height) = archive.size;
+        let cells = (0..width * height)
+            .map(|i| CellShard::new(archive.grid[i / height][i % height].clone()))
+            .collect();
+        Self { size: archive.size, cells, diversity_weight: archive.diversity_weight }
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        self.size
+    }
+
+    pub fn try_add(&self, prompt: AdversarialPrompt) -> AddOutcome {
+        let Some(index) = self.index(prompt.get_coordinates()) else {
+            return AddOutcome::OutOfBounds;
+        };
+        let cell = &self.cells[index];
+
+        if self.diversity_weight <= 0.0 {
+            // Быстрый отказ без блокировки: элита ячейки уже строго лучше
+            let best = cell.best();
+            if prompt.success_score < best {
+                return AddOutcome::Worse { incumbent: best };
+            }
+        }
+        // Тексты соседей читаем до блокировки своей ячейки, по одной блокировке за раз
+        let others = if self.diversity_weight > 0.0 { self.other_texts(index) } else { Vec::new() };
+        let others: Vec<&str> = others.iter().map(String::as_str).collect();
+
+        let mut elite = cell.elite.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let outcome = match elite.as_ref() {
+            None => AddOutcome::Filled,
+            Some(current) if self.replaces(&prompt, current, &others) => AddOutcome::Improved { incumbent: current.success_score },
+            Some(current) => AddOutcome::Worse { incumbent: current.success_score },
+        };
+        if outcome.is_new_elite() {
+            cell.best.store(prompt.success_score.to_bits(), Ordering::Release);
+            *elite = Some(prompt);
+        }
+        outcome
+    }
+
+    /// Stores `prompt` only if its cell is empty; whether it was stored
+    pub fn fill_empty(&self, prompt: AdversarialPrompt) -> bool {
+        let Some(index) = self.index(prompt.get_coordinates()) else {
+            return false;
+        };
+        let cell = &self.cells[index];
+        let mut elite = cell.elite.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if elite.is_some() {
+            return false;
+        }
+        cell.best.store(prompt.success_score.to_bits(), Ordering::Release);
+        *elite = Some(prompt);
+        true
+    }
+
+    fn index(&self, (x, y): (usize, usize)) -> Option<usize> {
+        (x < self.size.0 && y < self.size.1).then_some(x * self.size.1 + y)
+    }
+
+    /// Texts of the elites of every cell but `index`
+    fn other_texts(&self, index: usize) -> Vec<String> {
+        self.cells.iter()
+            .enumerate()
+            .filter(|(i, _)| *i != index)
+            .filter_map(|(_, cell)| {
+                let elite = cell.elite.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                elite.as_ref().map(|elite| elite.text.clone())
+            })
+            .collect()
+    }
+
+    /// `MapElitesArchive::replaces` against the texts of the other cells
+    fn replaces(&self, prompt: &AdversarialPrompt, incumbent: &AdversarialPrompt, others: &[&str]) -> bool {
+        if self.diversity_weight <= 0.0 {
+            return prompt.beats(incumbent);
+        }
+        let margin = diversity_margin(
+            (prompt.success_score, &prompt.text),
+            (incumbent.success_score, &incumbent.text),
+            others,
+            self.diversity_weight,
+        );
+        margin > 0.0 || (margin == 0.0 && prompt.beats(incumbent))
+    }
+
+    /// Number of occupied cells; a snapshot while workers are still adding
+    pub fn filled_cells(&self) -> usize {
+        self.cells.iter()
+            .filter(|cell| cell.best() > f64::NEG_INFINITY)
+            .count()
+    }
+
+    /// Writes the elites back into the grid of `archive`, which must have
+    /// the size this was built from
+    pub fn write_back(self, archive: &mut MapElitesArchive) {
+        let height = self.size.1;
+        for (i, cell) in self.cells.into_iter().enumerate() {
+            archive.grid[i / height][i % height] = cell.elite.into_inner().unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+    }
+}
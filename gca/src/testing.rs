@@ -0,0 +1,241 @@
+use crate::ai::embeddings::prompt_hash;
+use crate::http::client_builder;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Request headers that are not forwarded upstream: hop-by-hop ones and
+/// those the forwarding client sets itself
+const SKIPPED_HEADERS: [&str; 4] = ["host", "content-length", "connection", "transfer-encoding"];
+/// Largest request accepted by the recorder
+const MAX_REQUEST_BYTES: usize = 16 * 1024 * 1024;
+
+/// Whether the recorder talks to the real service or only to fixtures
+#[derive(Debug, Clone)]
+pub enum HttpMode {
+    /// Forward every request to `upstream` (e.g. `http://localhost:11434`)
+    /// and save the exchange
+    Record { upstream: String },
+    /// Answer from saved exchanges; unknown requests get a 404
+    Replay,
+}
+
+/// One saved request/response pair. Headers are not stored, so API keys
+/// never end up in fixtures.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpExchange {
+    pub method: String,
+    pub path: String,
+    pub request_body: String,
+    pub status: u16,
+    pub response_body: String,
+}
+
+/// Local HTTP server that records or replays backend traffic. Point a
+/// backend at `url()` instead of the real service:
+/// `Ollama::new(&recorder.url(), model)`.
+///
+/// Fixtures are named `<hash of method, path and body>-<n>.json`, where
+/// `n` counts identical requests, so a retried request can get a
+/// different answer. Replay falls back to the last recorded answer.
+pub struct HttpRecorder {
+    addr: SocketAddr,
+    server: tokio::task::JoinHandle<()>,
+}
+
+struct RecorderState {
+    mode: HttpMode,
+    fixtures: PathBuf,
+    client: reqwest::Client,
+    /// Occurrences of each request key so far
+    seen: Mutex<HashMap<u64, usize>>,
+}
+
+impl HttpRecorder {
+    pub async fn start(mode: HttpMode, fixtures: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let fixtures = fixtures.into();
+        if let HttpMode::Record { .. } = mode {
+            std::fs::create_dir_all(&fixtures)?;
+        }
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(RecorderState {
+            mode,
+            fixtures,
+            client: client_builder().build()?,
+            seen: Mutex::new(HashMap::new()),
+        });
+
+        let server = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let state = Arc::clone(&state);
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(stream, &state).await {
+                        eprintln!("⚠️  HTTP recorder: {}", e);
+                    }
+                });
+            }
+        });
+
+        Ok(Self { addr, server })
+    }
+
+    /// Base URL to give to a backend
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+}
+
+impl Drop for HttpRecorder {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+/// Parsed request: method, path, headers and body
+struct RawRequest {
+    method: String,
+    path: String,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+async fn handle_connection(mut stream: TcpStream, state: &RecorderState) -> anyhow::Result<()> {
+    let request = read_request(&mut stream).await?;
+    let key = request_key(&request.method, &request.path, &request.body);
+    let index = {
+        let mut seen = state.seen.lock().unwrap_or_else(|e| e.into_inner());
+        let count = seen.entry(key).or_insert(0);
+        *count += 1;
+        *count - 1
+    };
+
+    let (status, body) = match &state.mode {
+        HttpMode::Record { upstream } => {
+            let exchange = forward(&state.client, upstream, &request).await?;
+            let path = fixture_path(&state.fixtures, key, index);
+            std::fs::write(&path, serde_json::to_string_pretty(&exchange)?)?;
+            (exchange.status, exchange.response_body)
+        }
+        HttpMode::Replay => match load_fixture(&state.fixtures, key, index) {
+            Some(exchange) => (exchange.status, exchange.response_body),
+            None => (404, serde_json::json!({
+                "error": format!("no fixture for {} {}", request.method, request.path)
+            }).to_string()),
+        },
+    };
+
+    let reason = reqwest::StatusCode::from_u16(status).ok()
+        .and_then(|code| code.canonical_reason())
+        .unwrap_or("");
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status, reason, body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+async fn read_request(stream: &mut TcpStream) -> anyhow::Result<RawRequest> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 8192];
+
+    // Читаем до конца заголовков, затем тело по Content-Length
+    let header_end = loop {
+        if let Some(pos) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            anyhow::bail!("connection closed before the request headers ended");
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+        if buffer.len() > MAX_REQUEST_BYTES {
+            anyhow::bail!("request headers too large");
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..header_end]).into_owned();
+    let mut lines = head.lines();
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default().to_string();
+    let path = request_line.next().unwrap_or("/").to_string();
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+        .collect();
+
+    let length = headers.iter()
+        .find(|(name, _)| name == "content-length")
+        .and_then(|(_, value)| value.parse::<usize>().ok())
+        .unwrap_or(0);
+    if length > MAX_REQUEST_BYTES {
+        anyhow::bail!("request body too large ({} bytes)", length);
+    }
+    while buffer.len() < header_end + length {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            anyhow::bail!("connection closed before the request body ended");
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    }
+
+    let body = String::from_utf8_lossy(&buffer[header_end..header_end + length]).into_owned();
+    Ok(RawRequest { method, path, headers, body })
+}
+
+async fn forward(client: &reqwest::Client, upstream: &str, request: &RawRequest) -> anyhow::Result<HttpExchange> {
+    let method = reqwest::Method::from_bytes(request.method.as_bytes())?;
+    let mut builder = client.request(method, format!("{}{}", upstream.trim_end_matches('/'), request.path));
+    for (name, value) in &request.headers {
+        if !SKIPPED_HEADERS.contains(&name.as_str()) {
+            builder = builder.header(name.as_str(), value.as_str());
+        }
+    }
+
+    let response = builder.body(request.body.clone()).send().await?;
+    let status = response.status().as_u16();
+    let response_body = response.text().await?;
+    Ok(HttpExchange {
+        method: request.method.clone(),
+        path: request.path.clone(),
+        request_body: request.body.clone(),
+        status,
+        response_body,
+    })
+}
+
+/// Key identifying identical requests in fixture names
+fn request_key(method: &str, path: &str, body: &str) -> u64 {
+    prompt_hash(&format!("{} {}\n{}", method, path, body))
+}
+
+/// Writes `exchange` as the answer to the `index`-th occurrence of its
+/// request, e.g. to script a failure before a recorded success
+pub fn save_fixture(fixtures: &Path, exchange: &HttpExchange, index: usize) -> anyhow::Result<PathBuf> {
+    std::fs::create_dir_all(fixtures)?;
+    let key = request_key(&exchange.method, &exchange.path, &exchange.request_body);
+    let path = fixture_path(fixtures, key, index);
+    std::fs::write(&path, serde_json::to_string_pretty(exchange)?)?;
+    Ok(path)
+}
+
+fn fixture_path(fixtures: &Path, key: u64, index: usize) -> PathBuf {
+    fixtures.join(format!("{:016x}-{}.json", key, index))
+}
+
+/// The `index`-th recorded answer to a request, or the last one recorded
+fn load_fixture(fixtures: &Path, key: u64, index: usize) -> Option<HttpExchange> {
+    (0..=index).rev()
+        .map(|i| fixture_path(fixtures, key, i))
+        .find(|path| path.is_file())
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+}
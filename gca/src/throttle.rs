@@ -0,0 +1,39 @@
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+/// Spaces out the requests of one pipeline stage: every `acquire` returns
+/// at least `interval` after the previous one, however many workers wait
+#[derive(Debug)]
+pub struct RateLimiter {
+    rate: f64,
+    interval: Duration,
+    next: Mutex<Option<Instant>>,
+}
+
+impl RateLimiter {
+    /// At most `rate` requests per second; `None` unless `rate` is positive
+    pub fn per_second(rate: f64) -> Option<Self> {
+        (rate.is_finite() && rate > 0.0).then(|| Self {
+            rate,
+            interval: Duration::from_secs_f64(1.0 / rate),
+            next: Mutex::new(None),
+        })
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    /// Waits for the next free slot
+    pub async fn acquire(&self) {
+        let start = {
+            let mut next = self.next.lock().await;
+            let now = Instant::now();
+            let start = next.map_or(now, |slot| slot.max(now));
+            *next = Some(start + self.interval);
+            start
+        };
+        tokio::time::sleep_until(start).await;
+    }
+}
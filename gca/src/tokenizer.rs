@@ -0,0 +1,98 @@
+/// Lightweight approximation of a BPE tokenizer.
+///
+/// Ollama does not expose the model tokenizer over HTTP, so token counts
+/// are estimated locally: text is pre-split into words, numbers and
+/// punctuation (leading spaces stick to the following piece, as in
+/// GPT/Llama tokenizers), then long pieces are cut into chunks whose size
+/// depends on the script — Latin words compress well, Cyrillic roughly
+/// half as well, CJK is about one token per character.
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+
+    for piece in pre_tokenize(text) {
+        let body = piece.trim_start();
+        let chunk_chars = chunk_size(body);
+        let chars: Vec<char> = piece.chars().collect();
+        let leading = chars.len() - body.chars().count();
+
+        // Короткие слова - один токен вместе с ведущим пробелом
+        if body.chars().count() <= chunk_chars + 2 {
+            tokens.push(piece);
+            continue;
+        }
+
+        let mut start = 0;
+        let mut end = leading + chunk_chars;
+        while start < chars.len() {
+            let stop = end.min(chars.len());
+            tokens.push(chars[start..stop].iter().collect());
+            start = stop;
+            end = start + chunk_chars;
+        }
+    }
+
+    tokens
+}
+
+/// Estimated number of tokens in `text`
+pub fn count_tokens(text: &str) -> usize {
+    tokenize(text).len()
+}
+
+/// Splits into runs of letters, digits (max 3), single punctuation
+/// characters, and whitespace that is not followed by a word
+fn pre_tokenize(text: &str) -> Vec<String> {
+    #[derive(PartialEq, Clone, Copy)]
+    enum Class { Letter, Digit, Space, Other }
+
+    let class = |c: char| {
+        if c.is_alphabetic() { Class::Letter }
+        else if c.is_numeric() { Class::Digit }
+        else if c.is_whitespace() { Class::Space }
+        else { Class::Other }
+    };
+
+    let mut pieces: Vec<String> = Vec::new();
+    let mut current = String::new();
+    let mut current_class = Class::Space;
+
+    for c in text.chars() {
+        let c_class = class(c);
+        let continues = match (current_class, c_class) {
+            (Class::Letter, Class::Letter) => true,
+            (Class::Digit, Class::Digit) => current.chars().filter(|c| c.is_numeric()).count() < 3,
+            (Class::Space, Class::Space) => true,
+            // Один ведущий пробел приклеивается к следующему слову
+            (Class::Space, _) => current == " ",
+            _ => false,
+        };
+
+        if !continues && !current.is_empty() {
+            pieces.push(std::mem::take(&mut current));
+        }
+        if !continues || current_class == Class::Space {
+            current_class = c_class;
+        }
+        current.push(c);
+    }
+
+    if !current.is_empty() {
+        pieces.push(current);
+    }
+
+    pieces
+}
+
+/// Characters per token for the dominant script of `word`
+fn chunk_size(word: &str) -> usize {
+    match word.chars().next() {
+        Some(c) if c.is_ascii() => 4,
+        Some(c) if is_cjk(c) => 1,
+        Some(_) => 3,
+        None => 1,
+    }
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32, 0x3040..=0x30FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xAC00..=0xD7AF)
+}